@@ -0,0 +1,151 @@
+use std::{
+    collections::VecDeque,
+    io::{self, BufRead, BufReader, Write},
+    net::{Shutdown, SocketAddr, TcpListener, TcpStream},
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+/// Amount of messages kept to replay to the servers that reconnect
+static HISTORY_SIZE: usize = 256;
+/// A server that doesn't read its events for this long is dropped, it
+/// receives the missed ones when it reconnects.
+static WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Default)]
+struct ChannelState {
+    history: VecDeque<(u64, String)>,
+    clients: Vec<TcpStream>,
+}
+
+/// Server-Sent Events endpoint hosted by the CLI, the Deno dev server
+/// subscribes to it to receive the [`crate::protocol::DevMessage`]s.
+///
+/// Each event has the build id as `id`, so a server that reconnects (with
+/// the `Last-Event-ID` header or `?since=<id>`) receives the messages it
+/// missed.
+#[derive(Debug)]
+pub struct DevChannel {
+    addr: SocketAddr,
+    state: Arc<Mutex<ChannelState>>,
+}
+
+impl DevChannel {
+    pub fn bind(host: &str) -> io::Result<DevChannel> {
+        let listener = TcpListener::bind((host, 0))?;
+        let addr = listener.local_addr()?;
+        let state = Arc::new(Mutex::new(ChannelState::default()));
+
+        let thread_state = state.clone();
+        thread::Builder::new()
+            .name("dev-channel".to_owned())
+            .spawn(move || {
+                for stream in listener.incoming().flatten() {
+                    let state = thread_state.clone();
+                    thread::spawn(move || {
+                        if let Err(err) = Self::accept(stream, state) {
+                            eprintln!("[DevChannel] {err}");
+                        }
+                    });
+                }
+            })?;
+
+        Ok(DevChannel { addr, state })
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    pub fn url(&self) -> String {
+        format!("http://{}/events", self.addr)
+    }
+
+    fn accept(mut stream: TcpStream, state: Arc<Mutex<ChannelState>>) -> io::Result<()> {
+        stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line)?;
+
+        let mut last_id = None;
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                if name.trim().eq_ignore_ascii_case("last-event-id") {
+                    last_id = value.trim().parse::<u64>().ok();
+                }
+            }
+        }
+
+        // GET /events?since=<id> HTTP/1.1
+        let target = request_line.split_whitespace().nth(1).unwrap_or("");
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        if path != "/events" {
+            stream.write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n")?;
+            return Ok(());
+        }
+        let since = query
+            .split('&')
+            .find_map(|param| param.strip_prefix("since="))
+            .and_then(|since| since.parse::<u64>().ok());
+        let last_id = last_id.or(since);
+
+        stream.write_all(
+            b"HTTP/1.1 200 OK\r\n\
+            Content-Type: text/event-stream\r\n\
+            Cache-Control: no-cache\r\n\
+            Connection: keep-alive\r\n\
+            Access-Control-Allow-Origin: *\r\n\r\n\
+            retry: 1000\n\n",
+        )?;
+        stream.set_read_timeout(None)?;
+        stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+
+        // The lock is held while replaying so no message is lost between the
+        // replay and the subscription.
+        let mut state = state.lock().unwrap();
+        if let Some(last_id) = last_id {
+            for (id, data) in state.history.iter().filter(|(id, _)| *id > last_id) {
+                stream.write_all(Self::format_event(*id, data).as_bytes())?;
+            }
+        }
+        state.clients.push(stream);
+
+        Ok(())
+    }
+
+    fn format_event(id: u64, data: &str) -> String {
+        format!("id: {id}\nevent: update\ndata: {data}\n\n")
+    }
+
+    /// Send the message to all the subscribed servers and keep it for
+    /// replay. Returns the amount of servers it was written to, a write only
+    /// means the kernel buffered it, not that the server handled it.
+    ///
+    /// A server that fails or times out is disconnected, it may have
+    /// received half of the event.
+    pub fn publish(&self, id: u64, data: String) -> usize {
+        let event = Self::format_event(id, &data);
+        let mut state = self.state.lock().unwrap();
+
+        state.clients.retain_mut(|client| {
+            let sent = client.write_all(event.as_bytes()).is_ok();
+            if !sent {
+                let _ = client.shutdown(Shutdown::Both); // Err(): Already closed
+            }
+            sent
+        });
+
+        state.history.push_back((id, data));
+        if state.history.len() > HISTORY_SIZE {
+            state.history.pop_front();
+        }
+
+        state.clients.len()
+    }
+}
@@ -0,0 +1,318 @@
+//! Lints of the discovered HTTP tree, run by `densky check` before any code
+//! is generated.
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use ahash::{HashMap, HashMapExt};
+use anstyle::{AnsiColor, Color, Style};
+use densky_core::walker::{WalkerContainer, WalkerTree};
+
+use crate::routes::dynamic_segment;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lint {
+    /// The same param name is used twice in a path: `/$id/posts/$id`
+    DuplicateParam,
+    /// Sibling dynamic segments: `/users/$id` and `/users/$name`, only the
+    /// first one is matched. A catch-all sibling is allowed.
+    DuplicateDynamic,
+    /// Two files handle the same path pattern
+    ShadowedRoute,
+    /// A middleware without routes to apply to
+    EmptyMiddleware,
+    /// A fallback that never runs because a catch-all route matches first
+    UnreachableFallback,
+}
+
+impl Lint {
+    pub fn severity(self) -> Severity {
+        match self {
+            Lint::DuplicateParam | Lint::DuplicateDynamic | Lint::ShadowedRoute => Severity::Error,
+            Lint::EmptyMiddleware | Lint::UnreachableFallback => Severity::Warning,
+        }
+    }
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Lint::DuplicateParam => "duplicate-param",
+            Lint::DuplicateDynamic => "duplicate-dynamic",
+            Lint::ShadowedRoute => "shadowed-route",
+            Lint::EmptyMiddleware => "empty-middleware",
+            Lint::UnreachableFallback => "unreachable-fallback",
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub lint: Lint,
+    pub file: PathBuf,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (label, color) = match self.lint.severity() {
+            Severity::Error => ("error", AnsiColor::BrightRed),
+            Severity::Warning => ("warning", AnsiColor::BrightYellow),
+        };
+        let severity = Style::new().fg_color(Some(Color::Ansi(color))).bold();
+        let location = Style::new().fg_color(Some(Color::Ansi(AnsiColor::BrightBlue)));
+
+        writeln!(
+            f,
+            "{}{label}[{}]{}: {}",
+            severity.render(),
+            self.lint,
+            severity.render_reset(),
+            self.message
+        )?;
+        write!(
+            f,
+            "  {}-->{} {}",
+            location.render(),
+            location.render_reset(),
+            self.file.display()
+        )
+    }
+}
+
+/// Catch-all segment (`$...rest` or `[...rest]`), matches the rest of the path
+fn is_catch_all(segment: &str) -> bool {
+    dynamic_segment(segment).is_some_and(|name| name.starts_with("..."))
+}
+
+fn last_segment(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+/// Path with the params replaced by `*`, two routes with the same pattern
+/// match the same requests.
+fn normalize(path: &str) -> String {
+    path.split('/')
+        .map(|segment| match dynamic_segment(segment) {
+            Some(name) if name.starts_with("...") => "**",
+            Some(_) => "*",
+            None => segment,
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Indexes of the dynamic segments that are siblings of an earlier one,
+/// with the index of the one that is matched instead: `(duplicate, first)`.
+///
+/// Catch-alls aren't duplicates, they match the deeper paths that a single
+/// dynamic segment doesn't.
+fn duplicate_dynamics(paths: &[String]) -> Vec<(usize, usize)> {
+    let mut first = None;
+    let mut duplicates = Vec::new();
+    for (index, path) in paths.iter().enumerate() {
+        let segment = last_segment(path);
+        if dynamic_segment(segment).is_none() || is_catch_all(segment) {
+            continue;
+        }
+        match first {
+            Some(first) => duplicates.push((index, first)),
+            None => first = Some(index),
+        }
+    }
+    duplicates
+}
+
+struct Linter<'a> {
+    container: &'a WalkerContainer,
+    routes_path: &'a Path,
+    /// Pattern to the first file that handles it
+    patterns: HashMap<String, PathBuf>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Linter<'_> {
+    fn file_path(&self, leaf: Option<usize>) -> Option<PathBuf> {
+        leaf.and_then(|leaf| self.container.get_leaf(leaf))
+            .map(|leaf| leaf.lock().unwrap().file_path.clone())
+    }
+
+    /// File to point to when the problem is the tree itself
+    fn tree_location(&self, tree: &WalkerTree) -> PathBuf {
+        [tree.leaf, tree.middleware, tree.fallback]
+            .into_iter()
+            .find_map(|leaf| self.file_path(leaf))
+            .unwrap_or_else(|| self.routes_path.join(tree.path.trim_start_matches('/')))
+    }
+
+    fn push(&mut self, lint: Lint, file: PathBuf, message: String) {
+        self.diagnostics.push(Diagnostic {
+            lint,
+            file,
+            message,
+        });
+    }
+
+    /// `unreachable` is set under a duplicate dynamic segment, its routes
+    /// are already reported so they aren't reported as shadowed too.
+    fn walk(&mut self, http_tree: Arc<Mutex<WalkerTree>>, unreachable: bool) {
+        let tree = http_tree.lock().unwrap();
+        let location = self.tree_location(&tree);
+
+        let mut params = Vec::new();
+        for name in tree.path.split('/').filter_map(dynamic_segment) {
+            let name = name.trim_start_matches("...");
+            if params.contains(&name) {
+                self.push(
+                    Lint::DuplicateParam,
+                    location.clone(),
+                    format!("param `{name}` is used twice in `{}`", tree.path),
+                );
+            }
+            params.push(name);
+        }
+
+        if let Some(source) = self.file_path(tree.leaf).filter(|_| !unreachable) {
+            let pattern = normalize(&tree.path);
+            match self.patterns.get(&pattern) {
+                Some(first) if *first != source => {
+                    let message = format!(
+                        "`{}` is shadowed by {}, it never matches",
+                        tree.path,
+                        first.display()
+                    );
+                    self.push(Lint::ShadowedRoute, source, message);
+                }
+                Some(_) => (),
+                None => {
+                    self.patterns.insert(pattern, source);
+                }
+            }
+        }
+
+        let children: Vec<_> = tree
+            .children
+            .iter()
+            .filter_map(|child| self.container.get_tree(*child))
+            .collect();
+
+        if let Some(middleware) = self.file_path(tree.middleware) {
+            if tree.leaf.is_none() && children.is_empty() {
+                self.push(
+                    Lint::EmptyMiddleware,
+                    middleware,
+                    format!("middleware of `{}` has no routes to apply to", tree.path),
+                );
+            }
+        }
+
+        let child_paths: Vec<String> = children
+            .iter()
+            .map(|child| child.lock().unwrap().path.clone())
+            .collect();
+
+        let catch_all = child_paths
+            .iter()
+            .find(|path| is_catch_all(last_segment(path)));
+
+        let duplicates = duplicate_dynamics(&child_paths);
+        for &(duplicate, first) in &duplicates {
+            let message = format!(
+                "`{}` and `{}` are dynamic segments of the same level, only `{}` is matched",
+                child_paths[duplicate], child_paths[first], child_paths[first]
+            );
+            let location = self.tree_location(&children[duplicate].lock().unwrap());
+            self.push(Lint::DuplicateDynamic, location, message);
+        }
+
+        if let (Some(fallback), Some(catch_all)) = (self.file_path(tree.fallback), catch_all) {
+            self.push(
+                Lint::UnreachableFallback,
+                fallback,
+                format!(
+                    "fallback of `{}` never runs, `{catch_all}` matches every request first",
+                    tree.path
+                ),
+            );
+        }
+
+        drop(tree);
+
+        for (index, child) in children.into_iter().enumerate() {
+            let is_duplicate = duplicates.iter().any(|(duplicate, _)| *duplicate == index);
+            self.walk(child, unreachable || is_duplicate);
+        }
+    }
+}
+
+/// Run every lint on the tree. The errors are sorted before the warnings.
+pub fn check_routes(
+    http_tree: Arc<Mutex<WalkerTree>>,
+    container: &WalkerContainer,
+    routes_path: &Path,
+) -> Vec<Diagnostic> {
+    let mut linter = Linter {
+        container,
+        routes_path,
+        patterns: HashMap::new(),
+        diagnostics: Vec::new(),
+    };
+    linter.walk(http_tree, false);
+
+    let mut diagnostics = linter.diagnostics;
+    diagnostics.sort_by_key(|diagnostic| diagnostic.lint.severity());
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paths(paths: &[&str]) -> Vec<String> {
+        paths.iter().map(|path| path.to_string()).collect()
+    }
+
+    #[test]
+    fn normalize_replaces_params() {
+        assert_eq!(normalize("/users/$id/posts"), "/users/*/posts");
+        assert_eq!(normalize("/users/[id]/posts"), "/users/*/posts");
+        assert_eq!(normalize("/files/$...path"), "/files/**");
+        assert_eq!(normalize("/files/[...path]"), "/files/**");
+        assert_eq!(normalize("/about"), "/about");
+    }
+
+    #[test]
+    fn catch_all_segments() {
+        assert!(is_catch_all("$...rest"));
+        assert!(is_catch_all("[...rest]"));
+        assert!(!is_catch_all("$rest"));
+        assert!(!is_catch_all("rest"));
+    }
+
+    #[test]
+    fn sibling_dynamics_are_reported_once() {
+        let children = paths(&["/users/me", "/users/$id", "/users/$name", "/users/[name]"]);
+        assert_eq!(duplicate_dynamics(&children), vec![(2, 1), (3, 1)]);
+    }
+
+    #[test]
+    fn catch_all_siblings_are_not_duplicates() {
+        let children = paths(&["/users/$id", "/users/[...rest]"]);
+        assert!(duplicate_dynamics(&children).is_empty());
+    }
+
+    #[test]
+    fn static_siblings_are_not_duplicates() {
+        let children = paths(&["/users/me", "/users/$id", "/users/new"]);
+        assert!(duplicate_dynamics(&children).is_empty());
+    }
+}
@@ -1,10 +1,11 @@
-use std::path::PathBuf;
+use std::{process, time::Instant};
 
-use clap::{arg, value_parser, ArgMatches, Command, ValueHint};
-use densky_core::{http::http_discover, utils::join_paths, CompileContext};
+use clap::{ArgMatches, Command};
+use densky_core::{http::http_discover, views::view_discover};
 
 use crate::{
-    compiler::{process_http, write_aux_files},
+    compiler::{compile_project, write_aux_files, BuildSummary, CompileOptions, CompileReport},
+    config::ProjectConfig,
     progress,
 };
 
@@ -12,47 +13,74 @@ pub struct BuildCommand;
 
 impl BuildCommand {
     pub fn command() -> Command {
-        Command::new("build").arg(
-            arg!([folder] "Proyect folder")
-                .default_value(".")
-                .value_hint(ValueHint::DirPath)
-                .value_parser(value_parser!(PathBuf)),
-        )
+        Command::new("build")
+            .args(ProjectConfig::args())
+            .arg(ProjectConfig::jobs_arg())
     }
 
     pub fn process(matches: &ArgMatches) {
-        let folder = matches.get_one::<PathBuf>("folder").unwrap();
-        let cwd = std::env::current_dir().unwrap();
-        let target_path: PathBuf = join_paths(folder, cwd).into();
-
-        println!("Building {}", target_path.display());
-
-        let compile_context = CompileContext {
-            output_dir: join_paths(".densky", &target_path),
-            routes_path: join_paths("src/routes", &target_path),
-            views_path: join_paths("src/views", &target_path),
-            static_path: join_paths("src/static", &target_path),
-            verbose: true,
-            static_prefix: "static/".to_owned(),
-        };
+        let config = ProjectConfig::load_or_exit(matches);
+
+        if !BuildCommand::build(&config) {
+            process::exit(1);
+        }
+    }
+
+    /// Build the project and print the summary. Returns `false` if there
+    /// were errors.
+    pub fn build(config: &ProjectConfig) -> bool {
+        println!("Building {}", config.target_path.display());
+
+        let compile_context = config.compile_context();
+        let mut summary = BuildSummary::default();
+        let mut errors = Vec::new();
 
         let progress = progress::create_spinner(Some("Discovering"));
 
-        match write_aux_files(&compile_context) {
-            Ok(_) => (),
-            Err(_) => {
-                // let _ = first_build_tx.send(false);
-                return;
-            }
+        let stage = Instant::now();
+        if let Err(e) = write_aux_files(&compile_context, &config.target_path, &config.server) {
+            progress.abandon();
+            eprintln!("Error writing aux files: {e}");
+            return false;
         };
+        summary.push("Aux files", 4, stage.elapsed());
         progress.tick();
 
+        let stage = Instant::now();
         let (mut http_container, http_tree) = http_discover(&compile_context);
+        progress.tick();
+        let views = view_discover(&compile_context);
+        summary.push(
+            "Discovery",
+            http_container.id_tree() + views.len(),
+            stage.elapsed(),
+        );
 
         progress.finish();
 
-        let progress = progress::create_bar(http_container.id_tree(), "Compiling");
+        compile_project(
+            &compile_context,
+            &mut http_container,
+            http_tree,
+            &views,
+            CompileOptions {
+                show_progress: true,
+                jobs: config.jobs,
+            },
+            &mut summary,
+            &mut errors,
+        );
+
+        println!(
+            "{summary}\n  Static files under /{} are served from {}",
+            compile_context.static_prefix, compile_context.static_path
+        );
+
+        if !errors.is_empty() {
+            eprintln!("\n{}", CompileReport(&errors));
+            return false;
+        }
 
-        process_http(http_tree.clone(), &mut http_container, Some(progress));
+        true
     }
 }
@@ -0,0 +1,67 @@
+use std::{path::Path, process};
+
+use anstyle::{AnsiColor, Color, Style};
+use clap::{arg, ArgAction, ArgMatches, Command};
+use densky_core::http::http_discover;
+
+use crate::{
+    check::{check_routes, Severity},
+    config::ProjectConfig,
+};
+
+pub struct CheckCommand;
+
+impl CheckCommand {
+    pub fn command() -> Command {
+        Command::new("check")
+            .about("Validate the routes without writing the output")
+            .long_about(
+                "Validate the routes without writing the output.\n\n\
+                Exit codes: 0 no problems, 1 errors (or warnings with --deny-warnings)",
+            )
+            .args(ProjectConfig::args())
+            .arg(arg!(-D --"deny-warnings" "Fail if there are warnings").action(ArgAction::SetTrue))
+    }
+
+    pub fn process(matches: &ArgMatches) {
+        let config = ProjectConfig::load_or_exit(matches);
+        let compile_context = config.compile_context();
+
+        let (http_container, http_tree) = http_discover(&compile_context);
+        let diagnostics = check_routes(
+            http_tree,
+            &http_container,
+            Path::new(&compile_context.routes_path),
+        );
+
+        for diagnostic in &diagnostics {
+            eprintln!("{diagnostic}\n");
+        }
+
+        let errors = diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.lint.severity() == Severity::Error)
+            .count();
+        let warnings = diagnostics.len() - errors;
+        let failed = errors > 0 || (warnings > 0 && matches.get_flag("deny-warnings"));
+
+        let color = if failed {
+            AnsiColor::BrightRed
+        } else {
+            AnsiColor::BrightGreen
+        };
+        let summary = Style::new().fg_color(Some(Color::Ansi(color))).bold();
+        eprintln!(
+            "{}Checked {}: {errors} error{}, {warnings} warning{}{}",
+            summary.render(),
+            config.target_path.display(),
+            if errors == 1 { "" } else { "s" },
+            if warnings == 1 { "" } else { "s" },
+            summary.render_reset()
+        );
+
+        if failed {
+            process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,34 @@
+use std::{fs, path::Path, process};
+
+use clap::{ArgMatches, Command};
+
+use crate::config::ProjectConfig;
+
+pub struct CleanCommand;
+
+impl CleanCommand {
+    pub fn command() -> Command {
+        Command::new("clean")
+            .about("Remove the output directory")
+            .args(ProjectConfig::args())
+    }
+
+    pub fn process(matches: &ArgMatches) {
+        let config = ProjectConfig::load_or_exit(matches);
+        let output_dir = Path::new(&config.output_dir);
+
+        // The config is rejected when the output dir contains the project or
+        // its sources, so removing it is safe
+
+        if !output_dir.exists() {
+            println!("Nothing to clean");
+            return;
+        }
+
+        if let Err(err) = fs::remove_dir_all(output_dir) {
+            eprintln!("Can't remove {}: {err}", output_dir.display());
+            process::exit(1);
+        }
+        println!("Removed {}", output_dir.display());
+    }
+}
@@ -1,5 +1,5 @@
 use std::{
-    ffi::OsStr,
+    io,
     path::PathBuf,
     process,
     sync::{
@@ -11,52 +11,81 @@ use std::{
 };
 
 use crate::{
-    compiler::{process_view, write_aux_files},
+    channel::DevChannel,
+    compiler::{write_aux_files, BuildSummary, CompileError, CompileReport, IncrementalCompiler},
+    config::{ProjectConfig, ServerConfig},
+    permissions::Permissions,
     progress,
-    watcher::{PollWatcher, WatchKind},
-};
-use clap::{arg, value_parser, ArgMatches, Command, ValueHint};
-use densky_core::{
-    http::http_discover,
-    utils::{join_paths, Fmt},
-    views::view_discover,
-    CompileContext, Manifest,
+    protocol::DevMessage,
+    supervisor::{Supervisor, STOP_TIMEOUT},
+    watcher::{
+        Debouncer, FilesWatcher, FolderWatcher, MultiWatcher, PathFilter, WatchEvent, WatchRoot,
+        WatcherHandle, WatcherKind,
+    },
 };
+use clap::{arg, value_parser, ArgMatches, Command};
+use densky_core::utils::{join_paths, Fmt};
+use signal_hook::{consts, flag};
+
+/// How often the watcher thread polls for changes
+static WATCH_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Files of the project root that the dev server loads on start, besides
+/// `densky.toml`. It can't reload them, so it's restarted when they change.
+static CONFIG_FILES: [&str; 4] = ["config.ts", "main.ts", "deno.json", "deno.jsonc"];
 
 pub struct DevCommand;
 
 impl DevCommand {
     pub fn command() -> Command {
-        Command::new("dev").arg(
-            arg!([folder] "Proyect folder")
-                .default_value(".")
-                .value_hint(ValueHint::DirPath)
-                .value_parser(value_parser!(PathBuf)),
-        )
+        Command::new("dev")
+            .args(ProjectConfig::args())
+            .arg(ProjectConfig::jobs_arg())
+            .args(ServerConfig::args())
+            .arg(
+                arg!(--watcher <WATCHER> "File watcher backend")
+                    .default_value("native")
+                    .value_parser(["poll", "native"]),
+            )
+            .arg(
+                arg!(--debounce <MS> "Milliseconds without changes before a rebuild")
+                    .default_value("100")
+                    .value_parser(value_parser!(u64)),
+            )
     }
 
     pub fn process(matches: &ArgMatches) {
-        let folder = matches.get_one::<PathBuf>("folder").unwrap();
-        let cwd = std::env::current_dir().unwrap();
-        let target_path: PathBuf = join_paths(folder, cwd).into();
-
-        let watching_path = target_path.clone();
-        let mut watching_poll = PollWatcher::new(watching_path).unwrap();
-
-        let target_path_main = target_path.clone();
-
-        let compile_context = CompileContext {
-            output_dir: join_paths(".densky", &target_path_main),
-            routes_path: join_paths("src/routes", &target_path_main),
-            views_path: join_paths("src/views", &target_path_main),
-            static_path: join_paths("src/static", &target_path_main),
-            verbose: true,
-            static_prefix: "static/".to_owned(),
+        let config = ProjectConfig::load_or_exit(matches);
+
+        let watcher_kind =
+            WatcherKind::from_name(matches.get_one::<String>("watcher").unwrap()).unwrap();
+        let debounce = Duration::from_millis(*matches.get_one::<u64>("debounce").unwrap());
+        let watch_setup = WatchSetup {
+            config: config.clone(),
+            kind: watcher_kind,
+            debounce,
         };
+        let watcher = match watch_setup.spawn() {
+            Ok(watcher) => watcher,
+            Err(err) => {
+                eprintln!("Can't start the watcher: {err}");
+                process::exit(1);
+            }
+        };
+
+        let compile_context = config.compile_context();
+
+        let server = config.server.with_free_port();
+        if server.port != config.server.port {
+            println!(
+                "Port {} is busy, using {} instead",
+                config.server.port, server.port
+            );
+        }
 
         let progress = progress::create_spinner(Some("Discovering"));
 
-        match write_aux_files(&compile_context) {
+        match write_aux_files(&compile_context, &config.target_path, &server) {
             Ok(_) => (),
             Err(e) => {
                 eprintln!("Error on first build: {e}");
@@ -65,106 +94,279 @@ impl DevCommand {
         };
         progress.tick();
 
-        let (http_container, http_tree) = http_discover(&compile_context);
-        progress.tick();
-
-        Manifest::update(&http_container, &compile_context).unwrap();
-        progress.tick();
-
-        let views = view_discover(&compile_context);
+        let mut errors = Vec::new();
 
+        let mut compiler = IncrementalCompiler::new(compile_context, config.jobs);
         progress.finish();
-        for view in views {
-            process_view(view);
-        }
 
-        println!(
-            "\x1B[2J\x1B[1;1H{}\n",
-            Fmt(|f| http_tree.lock().unwrap().display(f, &http_container))
-        );
+        compiler.compile_all(true, &mut BuildSummary::default(), &mut errors);
+
+        DevCommand::print_status(&compiler, &errors);
 
-        let mut deno = process::Command::new("deno")
-            .args(["run", "-A"])
-            .arg(format!("{}/.densky/dev.ts", target_path.display()))
+        let dev_entry = join_paths("dev.ts", &compiler.compile_context().output_dir);
+        let channel = DevChannel::bind("127.0.0.1").expect("can't open the dev channel");
+        let permissions = DevCommand::permissions(&config, &server, &channel);
+        let deno = Supervisor::new("deno", ["run".to_owned()])
+            .args(permissions.to_flags())
+            .args([dev_entry])
+            .env("DENSKY_DEV_CHANNEL", channel.url())
+            .env("DENSKY_DEV_LAST_ID", "0")
             .spawn()
             .expect("deno command failed to run");
 
+        // The flags are checked between updates, so a signal never
+        // interrupts a compilation and leaves half-written outputs.
         let term = Arc::new(AtomicBool::new(false));
-        let sigint =
-            signal_hook::flag::register(signal_hook::consts::SIGINT, Arc::clone(&term)).unwrap();
+        let rebuild = Arc::new(AtomicBool::new(false));
+        let mut signals = Vec::new();
+        for signal in [consts::SIGINT, consts::SIGTERM] {
+            signals.push(flag::register(signal, Arc::clone(&term)).unwrap());
+        }
+        #[cfg(unix)]
+        signals.push(flag::register(consts::SIGHUP, Arc::clone(&rebuild)).unwrap());
+
+        let mut session = DevSession {
+            compiler,
+            watcher,
+            watch_setup,
+            deno,
+            channel,
+            target_path: config.target_path,
+            config_file: config.config_file,
+            server,
+            build_id: 0,
+        };
 
         '_loop: loop {
-            DevCommand::handle_update(&compile_context, &mut watching_poll);
+            // Waits for the watcher thread up to 200ms
+            session.handle_update();
+
+            if rebuild.swap(false, Ordering::Relaxed) {
+                session.rebuild();
+            }
+
+            session.sync_last_id();
+            session.deno.tick();
 
             // wait to interrupt
             if term.load(Ordering::Relaxed) {
-                // TODO: Check memory leaks on this line
-                assert!(signal_hook::low_level::unregister(sigint));
-                let _ = deno.kill(); // Err(): Command wasn't running
+                for signal in signals {
+                    signal_hook::low_level::unregister(signal);
+                }
+                println!("Shutting down...");
+                session.deno.stop(STOP_TIMEOUT);
+                // Dropping the session stops the watcher thread
                 return;
             }
+        }
+    }
 
-            thread::sleep(Duration::from_millis(200));
+    /// Watch only the sources: the routes, views and static folders, and the
+    /// entry and config files of the project root. A folder that doesn't
+    /// exist is watched once it's created.
+    fn create_watcher(
+        config: &ProjectConfig,
+        kind: WatcherKind,
+        filter: PathFilter,
+    ) -> MultiWatcher {
+        let mut watcher = MultiWatcher::default();
+        let folders = [
+            (WatchRoot::Routes, &config.routes_path),
+            (WatchRoot::Views, &config.views_path),
+            (WatchRoot::Static, &config.static_path),
+        ];
+        for (root, folder) in folders {
+            let folder = PathBuf::from(folder);
+            watcher.push(Box::new(FolderWatcher::new(
+                kind,
+                root,
+                folder,
+                filter.clone(),
+            )));
         }
+
+        let config_files = CONFIG_FILES
+            .iter()
+            .map(|file| config.target_path.join(file))
+            .chain([config.config_file.clone()])
+            .collect();
+        watcher.push(Box::new(FilesWatcher::new(
+            WatchRoot::Config,
+            config_files,
+            &filter,
+        )));
+
+        watcher
     }
 
-    fn handle_update(compile_context: &CompileContext, watching_poll: &mut PollWatcher) {
-        let event = watching_poll.poll();
-        if event.len() != 0 {
-            let (http_container, http_tree) = http_discover(&compile_context);
-            let views = view_discover(&compile_context);
+    /// The dev server also rewrites the output and subscribes to the dev
+    /// channel.
+    fn permissions(
+        config: &ProjectConfig,
+        server: &ServerConfig,
+        channel: &DevChannel,
+    ) -> Permissions {
+        config
+            .permissions(server)
+            .read(config.target_path.to_string_lossy())
+            .write(config.output_dir.clone())
+            .net(channel.addr().to_string())
+            .env("DENSKY_DEV_CHANNEL")
+            .env("DENSKY_DEV_LAST_ID")
+    }
 
-            println!(
-                "\x1B[2J\x1B[1;1H{}\n",
-                Fmt(|f| http_tree.lock().unwrap().display(f, &http_container))
-            );
+    fn print_status(compiler: &IncrementalCompiler, errors: &[CompileError]) {
+        let http_tree = compiler.http_tree();
+        println!(
+            "\x1B[2J\x1B[1;1H{}\n",
+            Fmt(|f| http_tree
+                .lock()
+                .unwrap()
+                .display(f, compiler.http_container()))
+        );
 
-            for view in views {
-                process_view(view);
-            }
+        if !errors.is_empty() {
+            eprintln!("{}\n", CompileReport(errors));
+        }
+    }
+}
 
-            match Manifest::update(&http_container, &compile_context) {
-                Ok(_) => {}
-                Err(err) => {
-                    eprintln!("Error updating manifest: {err}")
-                }
+/// What is needed to start the watcher again if its thread dies
+struct WatchSetup {
+    config: ProjectConfig,
+    kind: WatcherKind,
+    debounce: Duration,
+}
+
+impl WatchSetup {
+    fn spawn(&self) -> io::Result<WatcherHandle> {
+        let filter = self.config.path_filter().map_err(|err| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Invalid ignore rules: {err}"),
+            )
+        })?;
+        let watcher = Box::new(Debouncer::new(
+            Box::new(DevCommand::create_watcher(&self.config, self.kind, filter)),
+            self.debounce,
+        ));
+        WatcherHandle::spawn(watcher, WATCH_INTERVAL)
+    }
+}
+
+/// State of the dev loop
+struct DevSession {
+    compiler: IncrementalCompiler,
+    watcher: WatcherHandle,
+    watch_setup: WatchSetup,
+    deno: Supervisor,
+    channel: DevChannel,
+    target_path: PathBuf,
+    config_file: PathBuf,
+    server: ServerConfig,
+    build_id: u64,
+}
+
+impl DevSession {
+    fn handle_update(&mut self) {
+        let events = match self.watcher.recv_timeout(Duration::from_millis(200)) {
+            Ok(events) => events,
+            Err(_) => {
+                self.respawn_watcher();
+                return;
             }
+        };
+        if events.is_empty() {
+            return;
+        }
+
+        // Only the routes and views are compiled, the static files are
+        // served from their folder and the config is loaded by the server
+        let sources: Vec<WatchEvent> = events
+            .iter()
+            .filter(|event| matches!(event.root, WatchRoot::Routes | WatchRoot::Views))
+            .cloned()
+            .collect();
 
-            DevCommand::send_update(event.iter().map(|e| (e.kind.clone(), &e.path)));
+        let mut errors = Vec::new();
+        let mut affected_routes = Vec::new();
+        if !sources.is_empty() {
+            affected_routes = self.compiler.update(&sources, &mut errors).affected_routes;
+            DevCommand::print_status(&self.compiler, &errors);
+        }
+        self.build_id += 1;
+
+        // The message is always published, a restarted server replays the
+        // ones it missed.
+        let message = DevMessage::new(
+            &self.target_path,
+            self.build_id,
+            &events,
+            affected_routes,
+            &errors,
+        );
+        let receivers = self.channel.publish(self.build_id, message.to_json());
+
+        if events.iter().any(|event| event.path == self.config_file) {
+            eprintln!(
+                "[Dev] {} changed, restart densky dev to apply it",
+                self.config_file.display()
+            );
+        }
+
+        self.sync_last_id();
+        if events.iter().any(|event| event.root == WatchRoot::Config) {
+            // The server can't reload its config, it must be restarted
+            self.deno.restart();
+        } else if !self.deno.is_running() {
+            self.deno.on_change();
+        } else if receivers == 0 {
+            eprintln!(
+                "[Dev] The dev server isn't connected, update #{} will be replayed when it connects",
+                self.build_id
+            );
         }
     }
 
-    pub fn send_update<I, P>(files: I)
-    where
-        I: Iterator<Item = (WatchKind, P)>,
-        P: AsRef<OsStr>,
-    {
-        let mut files_json = "[".to_owned();
-        for file in files {
-            use WatchKind::*;
-            let kind = match file.0 {
-                Create => "create",
-                Remove => "remove",
-                Modify => "modify",
-            };
-            files_json += "[\"";
-            files_json += kind;
-            files_json += "\",\"";
-            files_json += file.1.as_ref().to_str().unwrap();
-            files_json += "\"],";
+    /// A (re)started server loads the outputs of the current build from
+    /// disk, so it only asks for the updates published after it. Nothing
+    /// is lost if it crashes before handling the updates it was sent.
+    fn sync_last_id(&mut self) {
+        self.deno
+            .set_env("DENSKY_DEV_LAST_ID", self.build_id.to_string());
+    }
+
+    /// The watcher thread stopped (it panicked), start a new one. The wait
+    /// keeps the loop from spinning while it can't be started.
+    fn respawn_watcher(&mut self) {
+        eprintln!("[Watcher] The watcher stopped, restarting it");
+        thread::sleep(Duration::from_secs(1));
+
+        match self.watch_setup.spawn() {
+            Ok(watcher) => self.watcher = watcher,
+            Err(err) => eprintln!("[Watcher] Can't restart the watcher: {err}"),
         }
-        files_json.pop();
-        files_json += "]";
-        // TODO: print good error
-        let res = ureq::post("http://localhost:8000/$/dev")
-            .set("Content-Type", "application/json")
-            .send_string(&files_json);
-
-        if let Err(err) = res {
-            match err {
-                ureq::Error::Status(_, _) => (),
-                ureq::Error::Transport(err) => println!("[Dev Error] {}", err),
-            }
+    }
+
+    /// Compile the whole project again (`SIGHUP`)
+    fn rebuild(&mut self) {
+        let mut errors = Vec::new();
+        if let Err(err) = write_aux_files(
+            self.compiler.compile_context(),
+            &self.target_path,
+            &self.server,
+        ) {
+            eprintln!("Error writing aux files: {err}");
         }
+
+        self.compiler.rediscover();
+        self.compiler
+            .compile_all(false, &mut BuildSummary::default(), &mut errors);
+        self.build_id += 1;
+
+        DevCommand::print_status(&self.compiler, &errors);
+
+        let message = DevMessage::full_reload(&self.target_path, self.build_id, &errors);
+        self.channel.publish(self.build_id, message.to_json());
     }
 }
@@ -1,5 +1,17 @@
 mod build;
+mod check;
+mod clean;
 mod dev;
+mod new;
+mod openapi;
+mod routes;
+mod start;
 
 pub use build::BuildCommand;
+pub use check::CheckCommand;
+pub use clean::CleanCommand;
 pub use dev::DevCommand;
+pub use new::NewCommand;
+pub use openapi::OpenApiCommand;
+pub use routes::RoutesCommand;
+pub use start::StartCommand;
@@ -0,0 +1,94 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process,
+};
+
+use clap::{arg, value_parser, ArgAction, ArgMatches, Command, ValueHint};
+use densky_core::utils::join_paths;
+
+use crate::templates::{get_template, PROJECT_DIRS, TEMPLATE_NAMES};
+
+pub struct NewCommand;
+
+impl NewCommand {
+    pub fn command() -> Command {
+        Command::new("new")
+            .about("Create a new project")
+            .visible_alias("init")
+            .arg(
+                arg!([folder] "Proyect folder")
+                    .default_value(".")
+                    .value_hint(ValueHint::DirPath)
+                    .value_parser(value_parser!(PathBuf)),
+            )
+            .arg(
+                arg!(-t --template <TEMPLATE> "Project template")
+                    .default_value("minimal")
+                    .value_parser(TEMPLATE_NAMES),
+            )
+            .arg(
+                arg!(-f --force "Write the files even if the folder isn't empty, overwriting the existing ones")
+                    .action(ArgAction::SetTrue),
+            )
+    }
+
+    pub fn process(matches: &ArgMatches) {
+        let folder = matches.get_one::<PathBuf>("folder").unwrap();
+        let template = matches.get_one::<String>("template").unwrap();
+        let force = matches.get_flag("force");
+        let cwd = std::env::current_dir().unwrap();
+        let target_path: PathBuf = join_paths(folder, cwd).into();
+
+        let is_empty = match fs::read_dir(&target_path) {
+            Ok(mut dir) => dir.next().is_none(),
+            Err(_) => true,
+        };
+        if !is_empty && !force {
+            eprintln!(
+                "{} is not empty, use --force to write the project anyway",
+                target_path.display()
+            );
+            process::exit(1);
+        }
+
+        let name = target_path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "densky-app".to_owned());
+
+        if let Err(err) = Self::write_template(&target_path, template, &name) {
+            eprintln!("Error creating project: {err}");
+            process::exit(1);
+        }
+
+        println!(
+            "Created {name} ({template}) at {}\n\nNext steps:",
+            target_path.display()
+        );
+        if folder.as_os_str() != "." {
+            println!("  cd {}", folder.display());
+        }
+        println!("  densky dev");
+    }
+
+    fn write_template(target_path: &Path, template: &str, name: &str) -> std::io::Result<()> {
+        for dir in PROJECT_DIRS {
+            fs::create_dir_all(target_path.join(dir))?;
+        }
+
+        // Template names are validated by clap
+        for file in get_template(template).unwrap() {
+            let path = target_path.join(file.path);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            // Only possible with `--force`
+            let action = if path.exists() { "overwrite" } else { "create" };
+            fs::write(&path, file.content.replace("{{name}}", name))?;
+            println!("  {action} {}", file.path);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,82 @@
+use std::{fs, path::PathBuf, process};
+
+use clap::{arg, value_parser, ArgMatches, Command, ValueHint};
+use densky_core::{http::http_discover, utils::join_paths};
+
+use crate::{config::ProjectConfig, openapi::build_document, routes::RouteTable};
+
+pub struct OpenApiCommand;
+
+impl OpenApiCommand {
+    pub fn command() -> Command {
+        Command::new("openapi")
+            .about("Generate an OpenAPI document of the routes")
+            .args(ProjectConfig::args())
+            .args([
+                arg!(-f --format <FORMAT> "Output format (default: from the output extension or json)")
+                    .value_parser(["json", "yaml"]),
+                arg!(-o --out <FILE> "Write the document to a file instead of stdout")
+                    .value_hint(ValueHint::FilePath)
+                    .value_parser(value_parser!(PathBuf)),
+                arg!(--title <TITLE> "API title (default: project folder name)"),
+                arg!(--"api-version" <VERSION> "API version").default_value("1.0.0"),
+            ])
+    }
+
+    pub fn process(matches: &ArgMatches) {
+        let config = ProjectConfig::load_or_exit(matches);
+        let compile_context = config.compile_context();
+
+        let (http_container, http_tree) = http_discover(&compile_context);
+        let table = RouteTable::build(http_tree, &http_container, &config.target_path);
+
+        let title = matches
+            .get_one::<String>("title")
+            .cloned()
+            .unwrap_or_else(|| {
+                config
+                    .target_path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| "Densky API".to_owned())
+            });
+        let version = matches.get_one::<String>("api-version").unwrap();
+
+        let document = match build_document(&table, &config.target_path, &title, version) {
+            Ok(document) => document,
+            Err(err) => {
+                eprintln!("{err}");
+                process::exit(1);
+            }
+        };
+
+        let out = matches
+            .get_one::<PathBuf>("out")
+            .map(|out| PathBuf::from(join_paths(out, std::env::current_dir().unwrap())));
+        let is_yaml = match matches.get_one::<String>("format") {
+            Some(format) => format == "yaml",
+            None => out.as_ref().is_some_and(|out| {
+                out.extension()
+                    .is_some_and(|ext| ext == "yaml" || ext == "yml")
+            }),
+        };
+
+        // Only json values, it can't fail
+        let output = if is_yaml {
+            serde_yaml::to_string(&document).unwrap()
+        } else {
+            serde_json::to_string_pretty(&document).unwrap() + "\n"
+        };
+
+        match out {
+            Some(out) => {
+                if let Err(err) = fs::write(&out, output) {
+                    eprintln!("Can't write {}: {err}", out.display());
+                    process::exit(1);
+                }
+                println!("OpenAPI document written to {}", out.display());
+            }
+            None => print!("{output}"),
+        }
+    }
+}
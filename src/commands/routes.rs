@@ -0,0 +1,41 @@
+use clap::{arg, ArgMatches, Command};
+use densky_core::{http::http_discover, utils::Fmt};
+
+use crate::{config::ProjectConfig, routes::RouteTable};
+
+pub struct RoutesCommand;
+
+impl RoutesCommand {
+    pub fn command() -> Command {
+        Command::new("routes")
+            .about("Print the discovered routes")
+            .args(ProjectConfig::args())
+            .arg(
+                arg!(-f --format <FORMAT> "Output format")
+                    .default_value("table")
+                    .value_parser(["table", "json", "tree"]),
+            )
+    }
+
+    pub fn process(matches: &ArgMatches) {
+        let config = ProjectConfig::load_or_exit(matches);
+        let compile_context = config.compile_context();
+
+        let (http_container, http_tree) = http_discover(&compile_context);
+
+        match matches.get_one::<String>("format").unwrap().as_str() {
+            "tree" => println!(
+                "{}",
+                Fmt(|f| http_tree.lock().unwrap().display(f, &http_container))
+            ),
+            format => {
+                let table = RouteTable::build(http_tree, &http_container, &config.target_path);
+                if format == "json" {
+                    println!("{}", table.to_json());
+                } else {
+                    println!("{table}");
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use std::{path::Path, process};
+
+use clap::{arg, ArgAction, ArgMatches, Command};
+use densky_core::utils::join_paths;
+
+use crate::{
+    commands::BuildCommand,
+    config::{ProjectConfig, ServerConfig},
+    permissions::Permissions,
+};
+
+pub struct StartCommand;
+
+impl StartCommand {
+    pub fn command() -> Command {
+        Command::new("start")
+            .about("Run the built project in production mode")
+            .args(ProjectConfig::args())
+            .args(ServerConfig::args())
+            .arg(arg!(--build "Build the project before start").action(ArgAction::SetTrue))
+    }
+
+    pub fn process(matches: &ArgMatches) {
+        let config = ProjectConfig::load_or_exit(matches);
+
+        if matches.get_flag("build") && !BuildCommand::build(&config) {
+            process::exit(1);
+        }
+
+        let entry = join_paths("start.ts", &config.output_dir);
+        if !Path::new(&entry).is_file() {
+            eprintln!("{entry} doesn't exist, run `densky build` first or use --build");
+            process::exit(1);
+        }
+
+        let server = &config.server;
+        let status = process::Command::new("deno")
+            .arg("run")
+            .args(StartCommand::permissions(&config).to_flags())
+            .arg(&entry)
+            .env("DENSKY_PORT", server.port.to_string())
+            .env("DENSKY_HOST", &server.host)
+            .status();
+
+        match status {
+            Ok(status) => process::exit(status.code().unwrap_or(1)),
+            Err(err) => {
+                eprintln!("deno command failed to run: {err}");
+                process::exit(1);
+            }
+        }
+    }
+
+    /// The project permissions plus the env vars of `start.ts`
+    fn permissions(config: &ProjectConfig) -> Permissions {
+        config
+            .permissions(&config.server)
+            .env("DENSKY_PORT")
+            .env("DENSKY_HOST")
+    }
+}
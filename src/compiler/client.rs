@@ -0,0 +1,183 @@
+use std::{
+    fmt::Write,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use densky_core::{
+    utils::join_paths,
+    walker::{WalkerContainer, WalkerTree},
+    CompileContext,
+};
+
+use crate::routes::{dynamic_segment, expand_method, OperationIds, RouteTable};
+
+use super::{write_output, CompileError};
+
+static CLIENT_RUNTIME: &str = r#"export interface ClientOptions {
+  /** Origin of the server, e.g. "https://api.example.com" (default: the current origin) */
+  baseUrl?: string;
+  /** Headers sent with every request */
+  headers?: HeadersInit;
+  fetch?: typeof fetch;
+}
+
+export type RequestOptions = Omit<RequestInit, "method">;
+
+function request(
+  options: ClientOptions,
+  method: string,
+  path: string,
+  init: RequestOptions = {},
+): Promise<Response> {
+  const base = options.baseUrl ?? globalThis.location?.origin;
+  const headers = new Headers(options.headers);
+  new Headers(init.headers).forEach((value, key) => headers.set(key, value));
+
+  return (options.fetch ?? fetch)(new URL(path, base), { ...init, method, headers });
+}
+"#;
+
+/// Template literal of the url path, with the params interpolated:
+/// `/users/$id` is `` `/users/${encodeURIComponent(params["id"])}` ``.
+fn url_template(path: &str) -> String {
+    let path = path
+        .split('/')
+        .map(|segment| match dynamic_segment(segment) {
+            Some(name) => match name.strip_prefix("...") {
+                // Catch-all keeps its slashes
+                Some(name) => format!("${{encodeURI(params[{name:?}])}}"),
+                None => format!("${{encodeURIComponent(params[{name:?}])}}"),
+            },
+            None => segment.replace('`', "\\`"),
+        })
+        .collect::<Vec<_>>()
+        .join("/");
+
+    if path.starts_with('/') {
+        format!("`{path}`")
+    } else {
+        format!("`/{path}`")
+    }
+}
+
+/// Source of `client.ts`: a `createClient` with one function per route and
+/// method.
+pub fn generate_client(table: &RouteTable) -> String {
+    let header = "// THIS FILE WAS GENERATED BY DENSKY-BACKEND (By Apika Luca)";
+    let mut functions = String::new();
+    let mut names = OperationIds::default();
+
+    for route in &table.routes {
+        let params = route
+            .params
+            .iter()
+            .map(|param| format!("{:?}: string", param.trim_start_matches("...")))
+            .collect::<Vec<_>>()
+            .join("; ");
+        let url = url_template(&route.path);
+
+        for method in route
+            .methods
+            .iter()
+            .flat_map(|method| expand_method(method))
+        {
+            let Some(name) = names.assign(&method.to_lowercase(), &route.path) else {
+                continue;
+            };
+
+            let args = if params.is_empty() {
+                "init?: RequestOptions".to_owned()
+            } else {
+                format!("params: {{ {params} }}, init?: RequestOptions")
+            };
+            // Can't fail, it writes to a String
+            let _ = write!(
+                functions,
+                "
+    /** {method} {path} */
+    {name}: ({args}) => request(options, \"{method}\", {url}, init),",
+                path = route.path,
+            );
+        }
+    }
+
+    format!(
+        "{header}
+{CLIENT_RUNTIME}
+export function createClient(options: ClientOptions = {{}}) {{
+  return {{{functions}
+  }};
+}}
+
+export type Client = ReturnType<typeof createClient>;
+"
+    )
+}
+
+/// Generate `client.ts` in the output dir from the discovered routes
+pub fn write_client(
+    compile_context: &CompileContext,
+    http_container: &WalkerContainer,
+    http_tree: Arc<Mutex<WalkerTree>>,
+) -> Result<(), CompileError> {
+    let routes_path = Path::new(&compile_context.routes_path);
+    let table = RouteTable::build(http_tree, http_container, routes_path);
+    let output_path = join_paths("client.ts", &compile_context.output_dir);
+
+    write_output(
+        routes_path,
+        Path::new(&output_path),
+        generate_client(&table),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::routes::Route;
+
+    fn route(path: &str, methods: &[&str]) -> Route {
+        Route {
+            path: path.to_owned(),
+            params: crate::routes::path_params(path),
+            methods: methods.iter().map(|method| method.to_string()).collect(),
+            source: format!("routes{path}.ts"),
+            middlewares: Vec::new(),
+            fallback: None,
+        }
+    }
+
+    #[test]
+    fn dynamic_segments_are_named_by_param() {
+        let table = RouteTable {
+            routes: vec![route("/users/$id", &["GET"]), route("/users/id", &["GET"])],
+        };
+        let client = generate_client(&table);
+        assert!(client.contains("getUsersById: (params: { \"id\": string }"));
+        assert!(client.contains("getUsersId: (init?: RequestOptions)"));
+    }
+
+    #[test]
+    fn colliding_names_are_numbered() {
+        let table = RouteTable {
+            routes: vec![
+                route("/users/$id", &["GET"]),
+                route("/users/by-id", &["GET"]),
+            ],
+        };
+        let client = generate_client(&table);
+        assert!(client.contains("getUsersById: (params"));
+        assert!(client.contains("getUsersById2: (init?: RequestOptions)"));
+    }
+
+    #[test]
+    fn explicit_method_wins_over_any() {
+        let table = RouteTable {
+            routes: vec![route("/health", &["GET", "ANY"])],
+        };
+        let client = generate_client(&table);
+        assert_eq!(client.matches("getHealth:").count(), 1);
+        assert!(client.contains("postHealth:"));
+    }
+}
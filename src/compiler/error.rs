@@ -0,0 +1,97 @@
+use std::{
+    fmt, io,
+    path::{Path, PathBuf},
+};
+
+use anstyle::{AnsiColor, Color, Style};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompilePhase {
+    /// Generating the code of a route, middleware or fallback
+    Generate,
+    /// Writing the generated code to the output dir
+    Write,
+    Manifest,
+}
+
+impl fmt::Display for CompilePhase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            CompilePhase::Generate => "generate",
+            CompilePhase::Write => "write",
+            CompilePhase::Manifest => "manifest",
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CompileError {
+    /// Source file that produced the error
+    pub file: PathBuf,
+    pub phase: CompilePhase,
+    pub message: String,
+}
+
+impl CompileError {
+    pub fn new(file: impl Into<PathBuf>, phase: CompilePhase, message: impl Into<String>) -> Self {
+        CompileError {
+            file: file.into(),
+            phase,
+            message: message.into(),
+        }
+    }
+
+    pub fn write(file: &Path, err: io::Error) -> Self {
+        CompileError::new(file, CompilePhase::Write, err.to_string())
+    }
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let error = Style::new()
+            .fg_color(Some(Color::Ansi(AnsiColor::BrightRed)))
+            .bold();
+        let location = Style::new().fg_color(Some(Color::Ansi(AnsiColor::BrightBlue)));
+
+        writeln!(
+            f,
+            "{}error[{}]{}: {}",
+            error.render(),
+            self.phase,
+            error.render_reset(),
+            self.message
+        )?;
+        write!(
+            f,
+            "  {}-->{} {}",
+            location.render(),
+            location.render_reset(),
+            self.file.display()
+        )
+    }
+}
+
+impl std::error::Error for CompileError {}
+
+/// Pretty report of all the errors of a compilation
+pub struct CompileReport<'a>(pub &'a [CompileError]);
+
+impl fmt::Display for CompileReport<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for error in self.0 {
+            writeln!(f, "{error}\n")?;
+        }
+
+        let summary = Style::new()
+            .fg_color(Some(Color::Ansi(AnsiColor::BrightRed)))
+            .bold();
+        write!(
+            f,
+            "{}Compilation failed with {} error{}{}",
+            summary.render(),
+            self.0.len(),
+            if self.0.len() == 1 { "" } else { "s" },
+            summary.render_reset()
+        )
+    }
+}
@@ -0,0 +1,370 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use ahash::HashMap;
+use densky_core::{
+    http::http_discover,
+    views::{view_discover, ViewLeaf},
+    walker::{WalkerContainer, WalkerTree},
+    CompileContext,
+};
+
+use crate::watcher::{split_renames, WatchEvent, WatchKind};
+
+use super::{
+    compile_project, expected_outputs, process_http_leaf, process_http_tree, process_view,
+    prune_outputs, update_manifest, write_client, BuildSummary, CompileError, CompileOptions,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RouteFileKind {
+    /// The handler of the tree, it's compiled inside the tree file
+    Index,
+    Middleware,
+    Fallback,
+}
+
+#[derive(Debug)]
+struct RouteFile {
+    leaf: usize,
+    kind: RouteFileKind,
+    /// Url path of the owner tree
+    route: String,
+    /// Output path of the trees from the root to the owner of the file. The
+    /// output path is used because the ids change between discoveries.
+    trees: Vec<PathBuf>,
+}
+
+/// Lookup from source files to the nodes of the discovered tree
+#[derive(Debug, Default)]
+struct RouteIndex {
+    files: HashMap<PathBuf, RouteFile>,
+    trees: HashMap<PathBuf, Arc<Mutex<WalkerTree>>>,
+}
+
+impl RouteIndex {
+    fn build(root: Arc<Mutex<WalkerTree>>, container: &WalkerContainer) -> RouteIndex {
+        let mut index = RouteIndex::default();
+        index.walk(root, container, Vec::new());
+        index
+    }
+
+    fn walk(
+        &mut self,
+        tree_ref: Arc<Mutex<WalkerTree>>,
+        container: &WalkerContainer,
+        mut chain: Vec<PathBuf>,
+    ) {
+        let tree = tree_ref.lock().unwrap();
+        chain.push(tree.output_path.clone());
+        self.trees
+            .insert(tree.output_path.clone(), tree_ref.clone());
+
+        let leaves = [
+            (tree.leaf, RouteFileKind::Index),
+            (tree.middleware, RouteFileKind::Middleware),
+            (tree.fallback, RouteFileKind::Fallback),
+        ];
+        for (leaf, kind) in leaves {
+            let Some(leaf) = leaf else {
+                continue;
+            };
+            let Some(leaf_ref) = container.get_leaf(leaf) else {
+                continue;
+            };
+            let file_path = leaf_ref.lock().unwrap().file_path.clone();
+            self.files.insert(
+                file_path,
+                RouteFile {
+                    leaf,
+                    kind,
+                    route: tree.path.clone(),
+                    trees: chain.clone(),
+                },
+            );
+        }
+
+        let children = tree.children.clone();
+        drop(tree);
+
+        for child in children {
+            if let Some(child) = container.get_tree(child) {
+                self.walk(child, container, chain.clone());
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct UpdateResult {
+    /// Url path of the routes whose output was regenerated
+    pub affected_routes: Vec<String>,
+}
+
+/// Keeps the discovered routes and views alive between the updates of the
+/// dev mode, so a change only regenerates the outputs that depend on it.
+pub struct IncrementalCompiler {
+    compile_context: CompileContext,
+    http_container: WalkerContainer,
+    http_tree: Arc<Mutex<WalkerTree>>,
+    route_index: RouteIndex,
+    views: Vec<ViewLeaf>,
+    jobs: usize,
+}
+
+impl IncrementalCompiler {
+    pub fn new(compile_context: CompileContext, jobs: usize) -> IncrementalCompiler {
+        let (http_container, http_tree) = http_discover(&compile_context);
+        let route_index = RouteIndex::build(http_tree.clone(), &http_container);
+        let views = view_discover(&compile_context);
+
+        IncrementalCompiler {
+            compile_context,
+            http_container,
+            http_tree,
+            route_index,
+            views,
+            jobs,
+        }
+    }
+
+    pub fn compile_context(&self) -> &CompileContext {
+        &self.compile_context
+    }
+
+    pub fn http_container(&self) -> &WalkerContainer {
+        &self.http_container
+    }
+
+    pub fn http_tree(&self) -> Arc<Mutex<WalkerTree>> {
+        self.http_tree.clone()
+    }
+
+    /// Discover all the routes and views again, discarding the current state.
+    pub fn rediscover(&mut self) {
+        let (http_container, http_tree) = http_discover(&self.compile_context);
+        self.route_index = RouteIndex::build(http_tree.clone(), &http_container);
+        self.http_container = http_container;
+        self.http_tree = http_tree;
+        self.views = view_discover(&self.compile_context);
+    }
+
+    /// Compile everything from scratch, see [`compile_project`]
+    pub fn compile_all(
+        &mut self,
+        show_progress: bool,
+        summary: &mut BuildSummary,
+        errors: &mut Vec<CompileError>,
+    ) {
+        compile_project(
+            &self.compile_context,
+            &mut self.http_container,
+            self.http_tree.clone(),
+            &self.views,
+            CompileOptions {
+                show_progress,
+                jobs: self.jobs,
+            },
+            summary,
+            errors,
+        );
+    }
+
+    /// Regenerate the outputs affected by the events.
+    pub fn update(
+        &mut self,
+        events: &[WatchEvent],
+        errors: &mut Vec<CompileError>,
+    ) -> UpdateResult {
+        // A rename regenerates the same as removing the old file and
+        // creating the new one
+        let events = &split_renames(events)[..];
+        let mut result = UpdateResult::default();
+        let routes_path = PathBuf::from(&self.compile_context.routes_path);
+        let views_path = PathBuf::from(&self.compile_context.views_path);
+
+        let mut routes_changed = false;
+        let mut views_changed = false;
+        let mut modified_routes = Vec::new();
+        let mut modified_views = Vec::new();
+
+        for event in events {
+            let is_modify = matches!(event.kind, WatchKind::Modify);
+            if event.path.starts_with(&routes_path) {
+                if is_modify {
+                    modified_routes.push(&event.path);
+                } else {
+                    routes_changed = true;
+                }
+            } else if event.path.starts_with(&views_path) {
+                if is_modify {
+                    modified_views.push(&event.path);
+                } else {
+                    views_changed = true;
+                }
+            }
+        }
+
+        if routes_changed {
+            self.rediscover_http(events, &routes_path, &mut result.affected_routes, errors);
+        }
+        let client_changed = routes_changed || !modified_routes.is_empty();
+        for path in modified_routes {
+            if let Some(route_file) = self.route_index.files.get(path.as_path()) {
+                result.affected_routes.push(route_file.route.clone());
+            }
+            self.recompile_route(path, errors);
+        }
+        result.affected_routes.sort();
+        result.affected_routes.dedup();
+
+        // The exported methods can change with any edit of a route
+        if client_changed {
+            if let Err(err) = write_client(
+                &self.compile_context,
+                &self.http_container,
+                self.http_tree.clone(),
+            ) {
+                errors.push(err);
+            }
+        }
+
+        if views_changed {
+            let old_views =
+                std::mem::replace(&mut self.views, view_discover(&self.compile_context));
+            for view in &self.views {
+                let is_new = !old_views
+                    .iter()
+                    .any(|old| old.file_path() == view.file_path());
+                if is_new {
+                    if let Err(err) = process_view(view) {
+                        errors.push(err);
+                    }
+                }
+            }
+        }
+        if routes_changed || views_changed {
+            let expected =
+                expected_outputs(&self.http_container, self.http_tree.clone(), &self.views);
+            if let Err(err) = prune_outputs(&self.compile_context, &expected) {
+                errors.push(err);
+            }
+        }
+
+        for path in modified_views {
+            if let Some(view) = self.views.iter().find(|view| view.file_path() == path) {
+                if let Err(err) = process_view(view) {
+                    errors.push(err);
+                }
+            }
+        }
+
+        result
+    }
+
+    /// Discover the routes again after files were added or removed, and
+    /// regenerate the trees that contain (or contained) those files.
+    fn rediscover_http(
+        &mut self,
+        events: &[WatchEvent],
+        routes_path: &Path,
+        affected_routes: &mut Vec<String>,
+        errors: &mut Vec<CompileError>,
+    ) {
+        let old_index = std::mem::take(&mut self.route_index);
+
+        let (http_container, http_tree) = http_discover(&self.compile_context);
+        self.http_container = http_container;
+        self.http_tree = http_tree;
+        self.route_index = RouteIndex::build(self.http_tree.clone(), &self.http_container);
+
+        let mut trees: Vec<PathBuf> = Vec::new();
+        let mut leaves = Vec::new();
+        for event in events {
+            if !event.path.starts_with(routes_path) {
+                continue;
+            }
+
+            let route_file = match event.kind {
+                WatchKind::Create => {
+                    let route_file = self.route_index.files.get(&event.path);
+                    // Index files are compiled with their tree
+                    if let Some(route_file) = route_file {
+                        if route_file.kind != RouteFileKind::Index {
+                            leaves.push(route_file.leaf);
+                        }
+                    }
+                    route_file
+                }
+                WatchKind::Remove => old_index.files.get(&event.path),
+                WatchKind::Modify | WatchKind::Rename { .. } => None,
+            };
+
+            if let Some(route_file) = route_file {
+                affected_routes.push(route_file.route.clone());
+                for tree in &route_file.trees {
+                    if !trees.contains(tree) {
+                        trees.push(tree.clone());
+                    }
+                }
+            }
+        }
+
+        for leaf in leaves {
+            if let Some(leaf) = self.http_container.get_leaf(leaf) {
+                if let Err(err) = process_http_leaf(leaf) {
+                    errors.push(err);
+                }
+            }
+        }
+
+        for tree in trees {
+            let Some(tree) = self.route_index.trees.get(&tree) else {
+                // The folder was removed
+                continue;
+            };
+            let mut tree = tree.lock().unwrap();
+            if let Err(err) = process_http_tree(&mut tree, &mut self.http_container) {
+                errors.push(err);
+            }
+        }
+
+        if let Err(err) = update_manifest(&self.http_container, &self.compile_context) {
+            errors.push(err);
+        }
+    }
+
+    fn recompile_route(&mut self, path: &Path, errors: &mut Vec<CompileError>) {
+        let Some(route_file) = self.route_index.files.get(path) else {
+            return;
+        };
+
+        let result = match route_file.kind {
+            RouteFileKind::Index => {
+                let tree = route_file
+                    .trees
+                    .last()
+                    .and_then(|tree| self.route_index.trees.get(tree));
+                match tree {
+                    Some(tree) => {
+                        let mut tree = tree.lock().unwrap();
+                        process_http_tree(&mut tree, &mut self.http_container)
+                    }
+                    None => Ok(()),
+                }
+            }
+            RouteFileKind::Middleware | RouteFileKind::Fallback => {
+                match self.http_container.get_leaf(route_file.leaf) {
+                    Some(leaf) => process_http_leaf(leaf),
+                    None => Ok(()),
+                }
+            }
+        };
+
+        if let Err(err) = result {
+            errors.push(err);
+        }
+    }
+}
@@ -0,0 +1,487 @@
+mod client;
+mod error;
+mod incremental;
+mod outputs;
+mod pool;
+
+pub use client::*;
+pub use error::*;
+pub use incremental::*;
+pub use outputs::*;
+pub use pool::*;
+
+use std::{
+    fmt, fs, io,
+    path::{Component, Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use densky_core::{
+    http::{HttpLeaf, HttpTree},
+    utils::{import_filename, join_paths},
+    views::ViewLeaf,
+    walker::{WalkerContainer, WalkerLeaf, WalkerTree},
+    CompileContext, Manifest,
+};
+use indicatif::ProgressBar;
+
+use crate::{config::ServerConfig, progress, watcher::DirIterator};
+
+/// Import specifier of `to` from a module in `from_dir`, e.g. `../config.ts`
+fn relative_import(from_dir: &Path, to: &Path) -> String {
+    fn components(path: &Path) -> Vec<Component<'_>> {
+        path.components()
+            .filter(|component| *component != Component::CurDir)
+            .collect()
+    }
+    let from_dir = components(from_dir);
+    let to = components(to);
+    let common = from_dir.iter().zip(&to).take_while(|(a, b)| a == b).count();
+
+    let mut segments = vec![".".to_owned(); usize::from(common == from_dir.len())];
+    segments.extend(from_dir[common..].iter().map(|_| "..".to_owned()));
+    segments.extend(
+        to[common..]
+            .iter()
+            .map(|component| component.as_os_str().to_string_lossy().into_owned()),
+    );
+    segments.join("/")
+}
+
+/// `project_path` is the folder of `config.ts`, its options are passed to the
+/// dev server with the paths of `compile_context`
+pub fn write_aux_files(
+    compile_context: &CompileContext,
+    project_path: &Path,
+    server: &ServerConfig,
+) -> io::Result<()> {
+    fs::create_dir_all(&compile_context.output_dir)?;
+
+    let header = "// THIS FILE WAS GENERATED BY DENSKY-BACKEND (By Apika Luca)";
+    // main.ts
+    fs::write(join_paths("main.ts", &compile_context.output_dir), format!("{header}
+import * as $Densky$ from \"densky/runtime.ts\";
+import httpHandler from \"{http_main}\";
+
+$Densky$.HTTPResponse.viewsPath = \"{}\";
+
+export default async function requestHandler(req: $Densky$.HTTPRequest, conn?: Deno.Conn): Promise<Response> {{
+  return await httpHandler(req);
+}}", join_paths("views", &compile_context.output_dir), http_main = import_filename("./http.main.ts")))?;
+
+    // http.main.ts
+    fs::write(join_paths("http.main.ts", &compile_context.output_dir), format!("{header}
+import * as $Densky$ from \"densky/runtime.ts\";
+import mainHandler from \"{http_index}\";
+
+function toResponse (
+  req: $Densky$.HTTPRequest,
+  response: Response | $Densky$.HTTPError | Error | void
+): Response {{
+  if (response instanceof Error) 
+    response = $Densky$.HTTPError.fromError(response);
+
+  if (response instanceof $Densky$.HTTPError) 
+    response = response.toResponse();
+
+  if (response instanceof Response) 
+    return new Response(response.body, {{
+      status: response.status,
+      statusText: response.statusText,
+      headers: Object.fromEntries([...req.headers.entries(), ...response.headers.entries()]),
+    }});
+
+  throw new Error(\"Unreachable code\");
+}}
+
+export default async function requestHandler(req: $Densky$.HTTPRequest): Promise<Response> {{
+  return toResponse(req, await mainHandler(req) ?? new $Densky$.HTTPError($Densky$.StatusCode.NOT_FOUND));
+}}", http_index = import_filename("./http/_index.ts")))?;
+
+    // dev.ts
+    fs::write(
+        join_paths("dev.ts", &compile_context.output_dir),
+        format!(
+            "{header}
+import {{ DevServer }} from \"densky/dev.ts\";
+import userOptions from \"{config}\";
+
+// The paths are resolved by the CLI from densky.toml and the flags, so the
+// runtime and the compiler always agree
+const compileOptions = {{
+  ...userOptions,
+  outputDir: {output_dir:?},
+  routesPath: {routes_path:?},
+  viewsPath: {views_path:?},
+  staticPath: {static_path:?},
+  staticPrefix: {static_prefix:?},
+  verbose: {verbose},
+}};
+
+// A restarted server asks for the updates after the build it started from
+const devChannel = Deno.env.get(\"DENSKY_DEV_CHANNEL\");
+const lastId = Deno.env.get(\"DENSKY_DEV_LAST_ID\");
+
+const server = new DevServer({{
+  port: {port},
+  hostname: \"{host}\",
+  verbose: true,
+  devChannel: devChannel && lastId ? `${{devChannel}}?since=${{lastId}}` : devChannel,
+}}, compileOptions);
+
+server.start();
+",
+            config = import_filename(&relative_import(
+                Path::new(&compile_context.output_dir),
+                &project_path.join("config.ts")
+            )),
+            output_dir = compile_context.output_dir,
+            routes_path = compile_context.routes_path,
+            views_path = compile_context.views_path,
+            static_path = compile_context.static_path,
+            static_prefix = compile_context.static_prefix,
+            verbose = compile_context.verbose,
+            port = server.port,
+            host = server.host
+        ),
+    )?;
+
+    // start.ts
+    fs::write(
+        join_paths("start.ts", &compile_context.output_dir),
+        format!(
+            "{header}
+import * as $Densky$ from \"densky/runtime.ts\";
+import requestHandler from \"{main}\";
+
+const port = Number(Deno.env.get(\"DENSKY_PORT\") ?? {port});
+const hostname = Deno.env.get(\"DENSKY_HOST\") ?? \"{host}\";
+
+Deno.serve({{
+  port,
+  hostname,
+  onListen: () => console.log(`Listening on http://${{hostname}}:${{port}}`),
+}}, (request) => requestHandler(new $Densky$.HTTPRequest(request)));
+",
+            main = import_filename("./main.ts"),
+            port = server.port,
+            host = server.host
+        ),
+    )?;
+
+    Ok(())
+}
+
+/// Time and amount of outputs of each compilation stage, printed at the end of
+/// a build.
+#[derive(Debug, Default)]
+pub struct BuildSummary {
+    stages: Vec<(&'static str, usize, Duration)>,
+}
+
+impl BuildSummary {
+    pub fn push(&mut self, stage: &'static str, count: usize, elapsed: Duration) {
+        self.stages.push((stage, count, elapsed));
+    }
+
+    pub fn total_elapsed(&self) -> Duration {
+        self.stages.iter().map(|stage| stage.2).sum()
+    }
+}
+
+impl fmt::Display for BuildSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (stage, count, elapsed) in &self.stages {
+            writeln!(
+                f,
+                "  \x1B[32m✓\x1B[0m {stage:12} {count:>5} files {:>8.2?}",
+                elapsed
+            )?;
+        }
+        write!(f, "  Done in {:.2?}", self.total_elapsed())
+    }
+}
+
+fn write_output(source: &Path, output_path: &Path, output: String) -> Result<(), CompileError> {
+    if let Some(parent) = output_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| CompileError::write(source, e))?;
+    }
+    fs::write(output_path, output).map_err(|e| CompileError::write(source, e))
+}
+
+pub fn process_http_leaf(http_leaf: Arc<Mutex<WalkerLeaf>>) -> Result<(), CompileError> {
+    let http_leaf = http_leaf.lock().unwrap();
+    let output = HttpLeaf::generate_file(&http_leaf).map_err(|e| {
+        CompileError::new(
+            &http_leaf.file_path,
+            CompilePhase::Generate,
+            format!("{e:?}"),
+        )
+    })?;
+
+    write_output(&http_leaf.file_path, &http_leaf.output_path, output)
+}
+
+/// Source file of the tree, used on error reports. The tree doesn't have an
+/// own file, so it's the file of its index leaf, or the output if there isn't.
+fn tree_source(http_tree: &WalkerTree, container: &WalkerContainer) -> PathBuf {
+    http_tree
+        .leaf
+        .and_then(|leaf| container.get_leaf(leaf))
+        .map(|leaf| leaf.lock().unwrap().file_path.clone())
+        .unwrap_or_else(|| http_tree.output_path.clone())
+}
+
+/// Generate the file of the tree (`_index.ts`), without its children.
+/// Returns the source used on error reports and the output.
+fn generate_http_tree(
+    http_tree: &mut WalkerTree,
+    container: &mut WalkerContainer,
+) -> Result<(PathBuf, String), CompileError> {
+    let source = tree_source(http_tree, container);
+    let output = HttpTree::generate_file(http_tree, container)
+        .map_err(|e| CompileError::new(&source, CompilePhase::Generate, format!("{e:?}")))?;
+
+    Ok((source, output))
+}
+
+/// Compile only the file of the tree (`_index.ts`), without its children.
+pub fn process_http_tree(
+    http_tree: &mut WalkerTree,
+    container: &mut WalkerContainer,
+) -> Result<(), CompileError> {
+    let (source, output) = generate_http_tree(http_tree, container)?;
+    write_output(&source, &http_tree.output_path, output)
+}
+
+/// Compile the tree and all its children, the errors are pushed to `errors`
+/// without stopping the walk.
+///
+/// The tree files are written with `jobs` threads, but they are generated
+/// one at a time: the generator needs the container mutably (and reads the
+/// child trees), so it runs behind a container lock. The middlewares and
+/// fallbacks are generated after them, also with `jobs` threads.
+///
+/// Returns the amount of written files
+pub fn process_http(
+    http_tree: Arc<Mutex<WalkerTree>>,
+    container: &mut WalkerContainer,
+    jobs: usize,
+    progress: Option<ProgressBar>,
+    errors: &mut Vec<CompileError>,
+) -> usize {
+    let mut trees = Vec::new();
+    let mut leaves = Vec::new();
+    collect_http_trees(http_tree, container, &mut trees, &mut leaves);
+
+    let mut count = 0;
+    let shared_container = Mutex::new(&mut *container);
+    let results = parallel_map(jobs, &trees, progress.as_ref(), |tree| {
+        // The container is locked before any tree, the generator locks the
+        // children while it runs
+        let mut container = shared_container.lock().unwrap();
+        let mut tree = tree.lock().unwrap();
+        let (source, output) = generate_http_tree(&mut tree, &mut container)?;
+        let output_path = tree.output_path.clone();
+        drop(tree);
+        drop(container);
+
+        write_output(&source, &output_path, output)
+    });
+    for result in results {
+        match result {
+            Ok(_) => count += 1,
+            Err(err) => errors.push(err),
+        }
+    }
+
+    if let Some(ref progress_bar) = progress {
+        progress_bar.inc_length(leaves.len() as u64);
+    }
+    let results = parallel_map(jobs, &leaves, progress.as_ref(), |leaf| {
+        process_http_leaf(leaf.clone())
+    });
+    for result in results {
+        match result {
+            Ok(_) => count += 1,
+            Err(err) => errors.push(err),
+        }
+    }
+
+    count
+}
+
+/// Collect the tree and all its children in `trees`, and their middlewares
+/// and fallbacks in `leaves`
+fn collect_http_trees(
+    http_tree: Arc<Mutex<WalkerTree>>,
+    container: &WalkerContainer,
+    trees: &mut Vec<Arc<Mutex<WalkerTree>>>,
+    leaves: &mut Vec<Arc<Mutex<WalkerLeaf>>>,
+) {
+    let locked = http_tree.lock().unwrap();
+    let children = locked.children.clone();
+    for leaf in [locked.fallback, locked.middleware].into_iter().flatten() {
+        leaves.push(container.get_leaf(leaf).unwrap());
+    }
+    drop(locked);
+    trees.push(http_tree);
+
+    for child in children {
+        collect_http_trees(container.get_tree(child).unwrap(), container, trees, leaves);
+    }
+}
+
+/// A view that the generator skips (`None`) isn't an error, it's just not
+/// written. Returns if the output was written.
+pub fn process_view(view: &ViewLeaf) -> Result<bool, CompileError> {
+    let source = view.file_path();
+    let Some(output) = view.generate_file() else {
+        return Ok(false);
+    };
+
+    write_output(source, &view.output_path(), output.0)?;
+    Ok(true)
+}
+
+/// Generate the views with `jobs` threads, the errors are pushed in the
+/// order of `views`.
+///
+/// Returns the amount of written files
+pub fn process_views(
+    views: &[ViewLeaf],
+    jobs: usize,
+    progress: Option<ProgressBar>,
+    errors: &mut Vec<CompileError>,
+) -> usize {
+    let mut count = 0;
+    for result in parallel_map(jobs, views, progress.as_ref(), process_view) {
+        match result {
+            Ok(true) => count += 1,
+            Ok(false) => (),
+            Err(err) => errors.push(err),
+        }
+    }
+
+    count
+}
+
+pub fn update_manifest(
+    container: &WalkerContainer,
+    compile_context: &CompileContext,
+) -> Result<(), CompileError> {
+    Manifest::update(container, compile_context).map_err(|e| {
+        CompileError::new(
+            &compile_context.output_dir,
+            CompilePhase::Manifest,
+            e.to_string(),
+        )
+    })
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct CompileOptions {
+    pub show_progress: bool,
+    /// Threads used to generate the routes and views
+    pub jobs: usize,
+}
+
+/// Compile all the discovered routes and views, update the manifest, map the
+/// static files and delete the outputs of removed sources. It's the shared
+/// pipeline of `build` and `dev`, so both produce the same output.
+pub fn compile_project(
+    compile_context: &CompileContext,
+    http_container: &mut WalkerContainer,
+    http_tree: Arc<Mutex<WalkerTree>>,
+    views: &[ViewLeaf],
+    options: CompileOptions,
+    summary: &mut BuildSummary,
+    errors: &mut Vec<CompileError>,
+) {
+    let CompileOptions {
+        show_progress,
+        jobs,
+    } = options;
+
+    let progress = show_progress.then(|| progress::create_bar(http_container.id_tree(), "Routes"));
+    let stage = Instant::now();
+    let count = process_http(
+        http_tree.clone(),
+        http_container,
+        jobs,
+        progress.clone(),
+        errors,
+    );
+    if let Some(progress) = progress {
+        progress.finish_and_clear();
+    }
+    summary.push("Routes", count, stage.elapsed());
+
+    let progress = show_progress.then(|| progress::create_bar(views.len(), "Views"));
+    let stage = Instant::now();
+    let count = process_views(views, jobs, progress.clone(), errors);
+    if let Some(progress) = progress {
+        progress.finish_and_clear();
+    }
+    summary.push("Views", count, stage.elapsed());
+
+    let stage = Instant::now();
+    match write_client(compile_context, http_container, http_tree.clone()) {
+        Ok(_) => summary.push("Client", 1, stage.elapsed()),
+        Err(err) => errors.push(err),
+    }
+
+    let stage = Instant::now();
+    match update_manifest(http_container, compile_context) {
+        Ok(_) => summary.push("Manifest", 1, stage.elapsed()),
+        Err(err) => errors.push(err),
+    }
+
+    let stage = Instant::now();
+    let count = count_static_files(compile_context);
+    summary.push("Static", count, stage.elapsed());
+
+    let stage = Instant::now();
+    let expected = expected_outputs(http_container, http_tree, views);
+    match prune_outputs(compile_context, &expected) {
+        Ok(removed) => summary.push("Pruned", removed.len(), stage.elapsed()),
+        Err(err) => errors.push(err),
+    }
+}
+
+/// Amount of files that will be served under `static_prefix`
+pub fn count_static_files(compile_context: &CompileContext) -> usize {
+    match fs::read_dir(&compile_context.static_path) {
+        Ok(dir) => DirIterator::new(dir).count(),
+        Err(_) => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relative_imports() {
+        let project = Path::new("/app");
+        let config = project.join("config.ts");
+        assert_eq!(
+            relative_import(&project.join(".densky"), &config),
+            "../config.ts"
+        );
+        assert_eq!(
+            relative_import(&project.join("./.densky"), &config),
+            "../config.ts"
+        );
+        assert_eq!(
+            relative_import(&project.join("build/out"), &config),
+            "../../config.ts"
+        );
+        assert_eq!(relative_import(project, &config), "./config.ts");
+        assert_eq!(
+            relative_import(Path::new("/tmp/out"), &config),
+            "../../app/config.ts"
+        );
+    }
+}
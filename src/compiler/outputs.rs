@@ -0,0 +1,103 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use ahash::HashSet;
+use densky_core::{
+    utils::join_paths,
+    views::ViewLeaf,
+    walker::{WalkerContainer, WalkerTree},
+    CompileContext,
+};
+
+use crate::watcher::DirIterator;
+
+use super::CompileError;
+
+/// Folders of the output dir that only contain generated files, the files of
+/// these that aren't produced by the compilation are pruned.
+static GENERATED_DIRS: [&str; 2] = ["http", "views"];
+
+/// Files produced by a compilation of the discovered routes and views
+pub fn expected_outputs(
+    http_container: &WalkerContainer,
+    http_tree: Arc<Mutex<WalkerTree>>,
+    views: &[ViewLeaf],
+) -> HashSet<PathBuf> {
+    let mut outputs = HashSet::default();
+    collect_tree_outputs(http_container, http_tree, &mut outputs);
+    outputs.extend(views.iter().map(|view| view.output_path()));
+    outputs
+}
+
+fn collect_tree_outputs(
+    http_container: &WalkerContainer,
+    http_tree: Arc<Mutex<WalkerTree>>,
+    outputs: &mut HashSet<PathBuf>,
+) {
+    let tree = http_tree.lock().unwrap();
+    outputs.insert(tree.output_path.clone());
+
+    // The index leaf is compiled inside the tree file
+    for leaf in [tree.middleware, tree.fallback].into_iter().flatten() {
+        if let Some(leaf) = http_container.get_leaf(leaf) {
+            outputs.insert(leaf.lock().unwrap().output_path.clone());
+        }
+    }
+
+    let children = tree.children.clone();
+    drop(tree);
+
+    for child in children {
+        if let Some(child) = http_container.get_tree(child) {
+            collect_tree_outputs(http_container, child, outputs);
+        }
+    }
+}
+
+/// Delete the generated files whose source was removed, and the folders that
+/// become empty. Returns the deleted files.
+pub fn prune_outputs(
+    compile_context: &CompileContext,
+    expected: &HashSet<PathBuf>,
+) -> Result<Vec<PathBuf>, CompileError> {
+    let mut removed = Vec::new();
+
+    for dir in GENERATED_DIRS {
+        let dir = PathBuf::from(join_paths(dir, &compile_context.output_dir));
+        let Ok(read_dir) = fs::read_dir(&dir) else {
+            continue;
+        };
+
+        for entry in DirIterator::new(read_dir) {
+            let path = entry.path();
+            if expected.contains(&path) {
+                continue;
+            }
+            fs::remove_file(&path).map_err(|e| CompileError::write(&path, e))?;
+            removed.push(path);
+        }
+
+        remove_empty_dirs(&dir).map_err(|e| CompileError::write(&dir, e))?;
+    }
+
+    Ok(removed)
+}
+
+/// Remove the empty folders inside `dir`, `dir` itself is kept
+fn remove_empty_dirs(dir: &Path) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            let path = entry.path();
+            remove_empty_dirs(&path)?;
+            if fs::read_dir(&path)?.next().is_none() {
+                fs::remove_dir(&path)?;
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,69 @@
+use std::{
+    num::NonZeroUsize,
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+};
+
+use indicatif::ProgressBar;
+
+/// Amount of workers when `--jobs` isn't given: one per CPU
+pub fn default_jobs() -> usize {
+    thread::available_parallelism()
+        .map(NonZeroUsize::get)
+        .unwrap_or(1)
+}
+
+/// Run `f` on every item with up to `jobs` threads. The results keep the
+/// order of the items whatever the order they finish in, and the progress
+/// bar is increased once per item.
+pub fn parallel_map<T, R, F>(
+    jobs: usize,
+    items: &[T],
+    progress: Option<&ProgressBar>,
+    f: F,
+) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let jobs = jobs.clamp(1, items.len().max(1));
+    let run = |item: &T| {
+        let result = f(item);
+        if let Some(progress) = progress {
+            progress.inc(1);
+        }
+        result
+    };
+
+    if jobs == 1 {
+        return items.iter().map(run).collect();
+    }
+
+    let next = AtomicUsize::new(0);
+    let mut results: Vec<(usize, R)> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..jobs)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut results = Vec::new();
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(item) = items.get(index) else {
+                            break;
+                        };
+                        results.push((index, run(item)));
+                    }
+                    results
+                })
+            })
+            .collect();
+
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().unwrap())
+            .collect()
+    });
+
+    results.sort_unstable_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
@@ -0,0 +1,357 @@
+use std::{
+    fmt, fs, io,
+    net::TcpListener,
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+    process,
+};
+
+use clap::{arg, value_parser, Arg, ArgAction, ArgMatches, ValueHint};
+use densky_core::{utils::join_paths, CompileContext};
+use serde::Deserialize;
+
+use crate::{
+    compiler::default_jobs,
+    permissions::{Permissions, PermissionsSection},
+    watcher::PathFilter,
+};
+
+pub static CONFIG_FILENAME: &str = "densky.toml";
+
+/// Raw content of `densky.toml`. Every key is optional, missing keys
+/// fallback to the defaults of [`ProjectConfig`].
+///
+/// ```toml
+/// output_dir = ".densky"
+/// routes_dir = "src/routes"
+/// views_dir = "src/views"
+/// static_dir = "src/static"
+/// static_prefix = "static/"
+/// verbose = true
+/// jobs = 4
+///
+/// [server]
+/// host = "localhost"
+/// port = 8000
+///
+/// [watch]
+/// exclude = ["*.log", "tmp/"]
+///
+/// [permissions]
+/// read = ["data"]
+/// env = ["DATABASE_URL"]
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigFile {
+    pub output_dir: Option<String>,
+    pub routes_dir: Option<String>,
+    pub views_dir: Option<String>,
+    pub static_dir: Option<String>,
+    pub static_prefix: Option<String>,
+    pub verbose: Option<bool>,
+    pub jobs: Option<usize>,
+    pub server: ServerSection,
+    pub watch: WatchSection,
+    pub permissions: PermissionsSection,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerSection {
+    pub host: Option<String>,
+    pub port: Option<u16>,
+}
+
+/// Globs with the `.gitignore` syntax, relative to the project folder. They
+/// are added to the `.gitignore` and `.denskyignore` of the project.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WatchSection {
+    /// When it isn't empty only the matching files are watched
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(PathBuf, io::Error),
+    Parse(PathBuf, toml::de::Error),
+    /// The output dir contains the project or a source folder, cleaning or
+    /// pruning it would delete them
+    OutputOverlap(PathBuf, PathBuf),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(path, err) => {
+                write!(f, "Can't read config file {}: {err}", path.display())
+            }
+            ConfigError::Parse(path, err) => {
+                write!(f, "Invalid config file {}:\n{err}", path.display())
+            }
+            ConfigError::OutputOverlap(output_dir, source) => write!(
+                f,
+                "Invalid output dir {}: it contains {}, cleaning the outputs would delete it",
+                output_dir.display(),
+                source.display()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Resolved project configuration, shared by every subcommand.
+///
+/// Priority: CLI flags > `densky.toml` > defaults.
+#[derive(Debug, Clone)]
+pub struct ProjectConfig {
+    pub target_path: PathBuf,
+    /// `densky.toml` or the `--config` file, it may not exist
+    pub config_file: PathBuf,
+    pub output_dir: String,
+    pub routes_path: String,
+    pub views_path: String,
+    pub static_path: String,
+    pub static_prefix: String,
+    pub verbose: bool,
+    /// Threads used to generate the code
+    pub jobs: usize,
+    pub server: ServerConfig,
+    pub watch: WatchSection,
+    /// Declared in `densky.toml`, the paths are resolved
+    pub permissions: PermissionsSection,
+}
+
+/// Address of the Deno server
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerConfig {
+    pub host: String,
+    pub port: u16,
+}
+
+impl ServerConfig {
+    /// Arguments of the commands that run the server
+    pub fn args() -> Vec<Arg> {
+        vec![
+            arg!(-p --port <PORT> "Server port (default: 8000)").value_parser(value_parser!(u16)),
+            arg!(--host <HOST> "Server hostname (default: localhost)"),
+        ]
+    }
+
+    pub fn url(&self) -> String {
+        format!("http://{}:{}", self.host, self.port)
+    }
+
+    /// Get the same address if the port is free, otherwise the next free port.
+    pub fn with_free_port(&self) -> ServerConfig {
+        let is_free = |port: u16| TcpListener::bind((self.host.as_str(), port)).is_ok();
+
+        let port = (self.port..=self.port.saturating_add(100))
+            .find(|port| is_free(*port))
+            .or_else(|| {
+                // Let the OS choose one
+                TcpListener::bind((self.host.as_str(), 0))
+                    .and_then(|listener| listener.local_addr())
+                    .map(|addr| addr.port())
+                    .ok()
+            })
+            .unwrap_or(self.port);
+
+        ServerConfig {
+            host: self.host.clone(),
+            port,
+        }
+    }
+}
+
+impl ProjectConfig {
+    /// Arguments that every project-aware subcommand accepts.
+    pub fn args() -> Vec<Arg> {
+        vec![
+            arg!([folder] "Proyect folder")
+                .default_value(".")
+                .value_hint(ValueHint::DirPath)
+                .value_parser(value_parser!(PathBuf)),
+            arg!(-c --config <FILE> "Config file (default: <folder>/densky.toml)")
+                .value_hint(ValueHint::FilePath)
+                .value_parser(value_parser!(PathBuf)),
+            arg!(--output <DIR> "Output directory").value_hint(ValueHint::DirPath),
+            arg!(--routes <DIR> "Routes directory").value_hint(ValueHint::DirPath),
+            arg!(--views <DIR> "Views directory").value_hint(ValueHint::DirPath),
+            arg!(--static <DIR> "Static files directory").value_hint(ValueHint::DirPath),
+            arg!(--"static-prefix" <PREFIX> "Url prefix for static files"),
+            arg!(-v --verbose "Verbose output")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("quiet"),
+            arg!(-q --quiet "Disable verbose output").action(ArgAction::SetTrue),
+        ]
+    }
+
+    /// Only for the subcommands that compile the project (`build`, `dev`)
+    pub fn jobs_arg() -> Arg {
+        arg!(-j --jobs <N> "Threads used to generate the code (default: one per CPU)")
+            .value_parser(value_parser!(NonZeroUsize))
+    }
+
+    pub fn load(matches: &ArgMatches) -> Result<ProjectConfig, ConfigError> {
+        let folder = matches.get_one::<PathBuf>("folder").unwrap();
+        let cwd = std::env::current_dir().unwrap();
+        let target_path: PathBuf = join_paths(folder, cwd).into();
+
+        let (config_file, file) = match matches.get_one::<PathBuf>("config") {
+            Some(config_path) => {
+                let config_path: PathBuf = join_paths(config_path, &target_path).into();
+                let file = Self::read_file(&config_path)?;
+                (config_path, file)
+            }
+            None => {
+                let config_path = target_path.join(CONFIG_FILENAME);
+                let file = if config_path.is_file() {
+                    Self::read_file(&config_path)?
+                } else {
+                    ConfigFile::default()
+                };
+                (config_path, file)
+            }
+        };
+
+        let flag = |name: &str| matches.get_one::<String>(name).cloned();
+        let resolve = |flag: Option<String>, file: Option<String>, default: &str| {
+            join_paths(
+                flag.or(file).unwrap_or_else(|| default.to_owned()),
+                &target_path,
+            )
+        };
+
+        // Server arguments are only defined on some commands
+        let server = ServerConfig {
+            host: matches
+                .try_get_one::<String>("host")
+                .ok()
+                .flatten()
+                .cloned()
+                .or(file.server.host)
+                .unwrap_or_else(|| "localhost".to_owned()),
+            port: matches
+                .try_get_one::<u16>("port")
+                .ok()
+                .flatten()
+                .copied()
+                .or(file.server.port)
+                .unwrap_or(8000),
+        };
+
+        let verbose = if matches.get_flag("verbose") {
+            true
+        } else if matches.get_flag("quiet") {
+            false
+        } else {
+            file.verbose.unwrap_or(true)
+        };
+
+        let mut permissions = file.permissions;
+        for path in permissions
+            .read
+            .iter_mut()
+            .chain(permissions.write.iter_mut())
+        {
+            *path = join_paths(&path, &target_path);
+        }
+
+        let config = ProjectConfig {
+            output_dir: resolve(flag("output"), file.output_dir, ".densky"),
+            routes_path: resolve(flag("routes"), file.routes_dir, "src/routes"),
+            views_path: resolve(flag("views"), file.views_dir, "src/views"),
+            static_path: resolve(flag("static"), file.static_dir, "src/static"),
+            static_prefix: flag("static-prefix")
+                .or(file.static_prefix)
+                .unwrap_or_else(|| "static/".to_owned()),
+            verbose,
+            jobs: matches
+                .try_get_one::<NonZeroUsize>("jobs")
+                .ok()
+                .flatten()
+                .map(|jobs| jobs.get())
+                .or(file.jobs)
+                .unwrap_or_else(default_jobs)
+                .max(1),
+            server,
+            watch: file.watch,
+            permissions,
+            config_file,
+            target_path,
+        };
+        config.check_output_dir()?;
+
+        Ok(config)
+    }
+
+    /// A misconfigured output dir (e.g. `output_dir = "."` or
+    /// `output_dir = "src"`) would be cleaned or pruned with the project or
+    /// its sources inside.
+    fn check_output_dir(&self) -> Result<(), ConfigError> {
+        let output_dir = Path::new(&self.output_dir);
+        let sources = [
+            self.target_path.as_path(),
+            Path::new(&self.routes_path),
+            Path::new(&self.views_path),
+            Path::new(&self.static_path),
+        ];
+        match sources
+            .into_iter()
+            .find(|source| source.starts_with(output_dir))
+        {
+            Some(source) => Err(ConfigError::OutputOverlap(
+                output_dir.to_path_buf(),
+                source.to_path_buf(),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Same as [`ProjectConfig::load`] but prints the error and exits.
+    pub fn load_or_exit(matches: &ArgMatches) -> ProjectConfig {
+        match Self::load(matches) {
+            Ok(config) => config,
+            Err(err) => {
+                eprintln!("{err}");
+                process::exit(1);
+            }
+        }
+    }
+
+    fn read_file(path: &Path) -> Result<ConfigFile, ConfigError> {
+        let content =
+            fs::read_to_string(path).map_err(|e| ConfigError::Io(path.to_path_buf(), e))?;
+        toml::from_str(&content).map_err(|e| ConfigError::Parse(path.to_path_buf(), e))
+    }
+
+    /// Default permissions of the project on `server` plus the declared ones
+    pub fn permissions(&self, server: &ServerConfig) -> Permissions {
+        Permissions::defaults(&self.compile_context(), server).extend(&self.permissions)
+    }
+
+    /// Files of the project that the dev mode watches
+    pub fn path_filter(&self) -> Result<PathFilter, ignore::Error> {
+        PathFilter::new(
+            &self.target_path,
+            Path::new(&self.output_dir),
+            &self.watch.include,
+            &self.watch.exclude,
+        )
+    }
+
+    pub fn compile_context(&self) -> CompileContext {
+        CompileContext {
+            output_dir: self.output_dir.clone(),
+            routes_path: self.routes_path.clone(),
+            views_path: self.views_path.clone(),
+            static_path: self.static_path.clone(),
+            verbose: self.verbose,
+            static_prefix: self.static_prefix.clone(),
+        }
+    }
+}
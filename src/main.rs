@@ -1,15 +1,26 @@
 extern crate anstyle;
 
+pub mod channel;
+pub mod check;
 pub mod commands;
 pub mod compiler;
+pub mod config;
+pub mod openapi;
+pub mod permissions;
 pub mod progress;
+pub mod protocol;
+pub mod routes;
+pub mod supervisor;
+pub mod templates;
 pub mod watcher;
 
 use anstyle::{AnsiColor, Color, Style};
 use clap::{builder::Styles, command};
 use commands::BuildCommand;
 
-use crate::commands::DevCommand;
+use crate::commands::{
+    CheckCommand, CleanCommand, DevCommand, NewCommand, OpenApiCommand, RoutesCommand, StartCommand,
+};
 
 fn main() {
     #[allow(unused_mut)]
@@ -36,8 +47,15 @@ fn main() {
                 .valid(Style::new().fg_color(Some(Color::Ansi(AnsiColor::BrightGreen))))
                 .invalid(Style::new().fg_color(Some(Color::Ansi(AnsiColor::BrightRed)))),
         )
+        .arg_required_else_help(true)
+        .subcommand(NewCommand::command())
         .subcommand(DevCommand::command())
-        .subcommand(BuildCommand::command());
+        .subcommand(BuildCommand::command())
+        .subcommand(StartCommand::command())
+        .subcommand(RoutesCommand::command())
+        .subcommand(CheckCommand::command())
+        .subcommand(OpenApiCommand::command())
+        .subcommand(CleanCommand::command());
 
     #[cfg(not(debug_assertions))]
     {
@@ -47,10 +65,17 @@ fn main() {
     let matches = command.get_matches();
 
     match matches.subcommand() {
+        Some(("new", sub_matches)) => NewCommand::process(sub_matches),
         Some(("dev", sub_matches)) => DevCommand::process(sub_matches),
         Some(("build", sub_matches)) => BuildCommand::process(sub_matches),
+        Some(("start", sub_matches)) => StartCommand::process(sub_matches),
+        Some(("routes", sub_matches)) => RoutesCommand::process(sub_matches),
+        Some(("check", sub_matches)) => CheckCommand::process(sub_matches),
+        Some(("openapi", sub_matches)) => OpenApiCommand::process(sub_matches),
+        Some(("clean", sub_matches)) => CleanCommand::process(sub_matches),
 
         Some((cmd_name, _)) => println!("Unknown command: {cmd_name}"),
-        None => todo!("Main entry"),
+        // `arg_required_else_help` prints the help when there's no subcommand
+        None => unreachable!(),
     }
 }
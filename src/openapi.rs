@@ -0,0 +1,340 @@
+//! OpenAPI 3.1 document of the discovered routes.
+//!
+//! Every route of the [`RouteTable`] is a path item, with an operation per
+//! exported method (`ANY` is expanded to every method) and the params of its
+//! dynamic segments. The generated operations can be documented with:
+//!
+//! - A JSDoc comment over the exported handler. The first paragraph is the
+//!   `summary`, the rest the `description`. `@tag <name>` adds a tag and
+//!   `@deprecated` marks the operation as deprecated.
+//! - A sidecar `<route>.openapi.yaml` next to the route file with a Path
+//!   Item object (e.g. `get: { responses: ... }`), it's merged over the
+//!   generated one so any field can be overridden.
+
+use std::{fs, path::Path};
+
+use ahash::HashMap;
+use serde_json::{json, Map, Value};
+
+use crate::routes::{
+    dynamic_segment, expand_method, exported_method, OperationIds, Route, RouteTable,
+};
+
+pub const OPENAPI_VERSION: &str = "3.1.0";
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct JsDoc {
+    pub summary: Option<String>,
+    pub description: Option<String>,
+    pub tags: Vec<String>,
+    pub deprecated: bool,
+}
+
+impl JsDoc {
+    fn parse(comment: &[&str]) -> JsDoc {
+        let mut doc = JsDoc::default();
+        let mut paragraphs: Vec<Vec<&str>> = vec![Vec::new()];
+
+        for line in comment {
+            if let Some(tag) = line.strip_prefix("@tag ") {
+                doc.tags.push(tag.trim().to_owned());
+            } else if line.starts_with("@deprecated") {
+                doc.deprecated = true;
+            } else if line.starts_with('@') {
+                // Other tags (@param, @returns, ...) aren't used
+            } else if line.is_empty() {
+                paragraphs.push(Vec::new());
+            } else {
+                paragraphs.last_mut().unwrap().push(line);
+            }
+        }
+
+        let mut paragraphs = paragraphs
+            .into_iter()
+            .filter(|paragraph| !paragraph.is_empty())
+            .map(|paragraph| paragraph.join(" "));
+        doc.summary = paragraphs.next();
+        let description = paragraphs.collect::<Vec<_>>().join("\n\n");
+        doc.description = (!description.is_empty()).then_some(description);
+
+        doc
+    }
+}
+
+/// JSDoc comments (`/** ... */`) right above the exported methods
+pub fn jsdoc_comments(source: &str) -> HashMap<&'static str, JsDoc> {
+    let mut comments = HashMap::default();
+    let mut comment: Option<Vec<&str>> = None;
+    let mut current: Vec<&str> = Vec::new();
+    let mut in_comment = false;
+
+    for line in source.lines() {
+        let trimmed = line.trim();
+
+        if in_comment {
+            let (content, end) = match trimmed.strip_suffix("*/") {
+                Some(content) => (content, true),
+                None => (trimmed, false),
+            };
+            let content = content.strip_prefix('*').unwrap_or(content).trim();
+            current.push(content);
+            if end {
+                in_comment = false;
+                comment = Some(std::mem::take(&mut current));
+            }
+            continue;
+        }
+
+        if let Some(rest) = trimmed.strip_prefix("/**") {
+            match rest.strip_suffix("*/") {
+                Some(content) => comment = Some(vec![content.trim()]),
+                None => {
+                    in_comment = true;
+                    current.push(rest.trim());
+                }
+            }
+            continue;
+        }
+
+        if trimmed.is_empty() {
+            continue;
+        }
+
+        if let Some(method) = exported_method(line) {
+            if let Some(comment) = &comment {
+                comments.insert(method, JsDoc::parse(comment));
+            }
+        }
+        comment = None;
+    }
+
+    comments
+}
+
+/// Url path in OpenAPI syntax: `/users/$id` → `/users/{id}`
+pub fn openapi_path(path: &str) -> String {
+    let path = path
+        .split('/')
+        .map(|segment| match dynamic_segment(segment) {
+            Some(name) => format!("{{{}}}", name.trim_start_matches("...")),
+            None => segment.to_owned(),
+        })
+        .collect::<Vec<_>>()
+        .join("/");
+
+    if path.starts_with('/') {
+        path
+    } else {
+        format!("/{path}")
+    }
+}
+
+/// Merge `patch` into `target`, objects are merged recursively and any other
+/// value is replaced.
+fn merge(target: &mut Value, patch: Value) {
+    match (target, patch) {
+        (Value::Object(target), Value::Object(patch)) => {
+            for (key, value) in patch {
+                merge(target.entry(key).or_insert(Value::Null), value);
+            }
+        }
+        (target, patch) => *target = patch,
+    }
+}
+
+fn path_item(route: &Route, root: &Path, names: &mut OperationIds) -> Result<Value, String> {
+    let source_path = root.join(&route.source);
+    let source = fs::read_to_string(&source_path).unwrap_or_default();
+    let docs = jsdoc_comments(&source);
+
+    let parameters: Vec<Value> = route
+        .params
+        .iter()
+        .map(|param| {
+            json!({
+                "name": param.trim_start_matches("..."),
+                "in": "path",
+                "required": true,
+                "schema": { "type": "string" },
+            })
+        })
+        .collect();
+
+    let mut item = Map::new();
+    if !parameters.is_empty() {
+        item.insert("parameters".to_owned(), parameters.into());
+    }
+
+    for method in &route.methods {
+        let doc = docs.get(method.as_str()).cloned().unwrap_or_default();
+        for method in expand_method(method) {
+            let method = method.to_lowercase();
+            // A method exported twice (e.g. `GET` and `ANY`) keeps the explicit one
+            let Some(name) = names.assign(&method, &route.path) else {
+                continue;
+            };
+            let mut operation = json!({
+                "operationId": name,
+                "responses": {
+                    "default": { "description": "Response of the handler" }
+                },
+            });
+            let fields = operation.as_object_mut().unwrap();
+            if let Some(summary) = &doc.summary {
+                fields.insert("summary".to_owned(), summary.as_str().into());
+            }
+            if let Some(description) = &doc.description {
+                fields.insert("description".to_owned(), description.as_str().into());
+            }
+            if !doc.tags.is_empty() {
+                fields.insert("tags".to_owned(), doc.tags.clone().into());
+            }
+            if doc.deprecated {
+                fields.insert("deprecated".to_owned(), true.into());
+            }
+            item.insert(method, operation);
+        }
+    }
+
+    let mut item = Value::Object(item);
+
+    let sidecar = source_path.with_extension("openapi.yaml");
+    if sidecar.is_file() {
+        let content = fs::read_to_string(&sidecar)
+            .map_err(|e| format!("Can't read {}: {e}", sidecar.display()))?;
+        let annotations: Value = serde_yaml::from_str(&content)
+            .map_err(|e| format!("Invalid {}: {e}", sidecar.display()))?;
+        if !annotations.is_null() {
+            merge(&mut item, annotations);
+        }
+    }
+
+    Ok(item)
+}
+
+/// Build the document, `root` is the project folder (the paths of the table
+/// are relative to it).
+pub fn build_document(
+    table: &RouteTable,
+    root: &Path,
+    title: &str,
+    version: &str,
+) -> Result<Value, String> {
+    let mut paths = Map::new();
+    let mut names = OperationIds::default();
+    for route in &table.routes {
+        let item = path_item(route, root, &mut names)?;
+        merge(
+            paths
+                .entry(openapi_path(&route.path))
+                .or_insert(Value::Object(Map::new())),
+            item,
+        );
+    }
+
+    Ok(json!({
+        "openapi": OPENAPI_VERSION,
+        "info": {
+            "title": title,
+            "version": version,
+        },
+        "paths": paths,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route(path: &str, methods: &[&str]) -> Route {
+        Route {
+            path: path.to_owned(),
+            params: crate::routes::path_params(path),
+            methods: methods.iter().map(|method| method.to_string()).collect(),
+            source: format!("routes{path}.ts"),
+            middlewares: Vec::new(),
+            fallback: None,
+        }
+    }
+
+    #[test]
+    fn jsdoc_of_exported_methods() {
+        let source = "
+/**
+ * List the users
+ *
+ * Sorted by name.
+ * @tag users
+ * @deprecated
+ */
+export async function GET(req: HTTPRequest) {}
+
+/** Create a user */
+export const POST = (req: HTTPRequest) => {};
+
+/** Not exported */
+function helper() {}
+export function PUT() {}
+";
+        let docs = jsdoc_comments(source);
+        assert_eq!(
+            docs["GET"],
+            JsDoc {
+                summary: Some("List the users".to_owned()),
+                description: Some("Sorted by name.".to_owned()),
+                tags: vec!["users".to_owned()],
+                deprecated: true,
+            }
+        );
+        assert_eq!(docs["POST"].summary.as_deref(), Some("Create a user"));
+        assert!(!docs.contains_key("PUT"));
+    }
+
+    #[test]
+    fn openapi_paths() {
+        assert_eq!(openapi_path("/users/$id"), "/users/{id}");
+        assert_eq!(openapi_path("/users/[id]/posts"), "/users/{id}/posts");
+        assert_eq!(openapi_path("files/[...path]"), "/files/{path}");
+        assert_eq!(openapi_path("/"), "/");
+    }
+
+    #[test]
+    fn merge_objects_recursively() {
+        let mut target = json!({
+            "get": { "operationId": "getUsers", "responses": { "default": {} } },
+            "parameters": [1],
+        });
+        merge(
+            &mut target,
+            json!({
+                "get": { "responses": { "200": { "description": "Ok" } } },
+                "parameters": [2],
+            }),
+        );
+        assert_eq!(
+            target,
+            json!({
+                "get": {
+                    "operationId": "getUsers",
+                    "responses": { "default": {}, "200": { "description": "Ok" } },
+                },
+                "parameters": [2],
+            })
+        );
+    }
+
+    #[test]
+    fn operation_ids_are_unique() {
+        let table = RouteTable {
+            routes: vec![
+                route("/users/$id", &["GET", "ANY"]),
+                route("/users/by-id", &["GET"]),
+            ],
+        };
+        let document = build_document(&table, Path::new("/nonexistent"), "api", "1").unwrap();
+        let paths = &document["paths"];
+        assert_eq!(paths["/users/{id}"]["get"]["operationId"], "getUsersById");
+        assert_eq!(paths["/users/{id}"]["post"]["operationId"], "postUsersById");
+        assert_eq!(paths["/users/by-id"]["get"]["operationId"], "getUsersById2");
+    }
+}
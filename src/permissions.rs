@@ -0,0 +1,113 @@
+use std::collections::HashSet;
+
+use densky_core::CompileContext;
+use serde::Deserialize;
+
+use crate::config::ServerConfig;
+
+/// `[permissions]` section of `densky.toml`, granted to the Deno process on
+/// top of the defaults.
+///
+/// ```toml
+/// [permissions]
+/// net = ["api.example.com"]
+/// read = ["data"]
+/// write = ["data/uploads"]
+/// env = ["DATABASE_URL"]
+/// # Run with `-A` as before
+/// all = false
+/// ```
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PermissionsSection {
+    pub net: Vec<String>,
+    pub read: Vec<String>,
+    pub write: Vec<String>,
+    pub env: Vec<String>,
+    pub all: bool,
+}
+
+/// Permissions of the Deno process, turned into `--allow-*` flags.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Permissions {
+    pub net: Vec<String>,
+    pub read: Vec<String>,
+    pub write: Vec<String>,
+    pub env: Vec<String>,
+    pub all: bool,
+}
+
+impl Permissions {
+    /// What every project needs: serve on its address and read the output
+    /// and the project sources.
+    pub fn defaults(compile_context: &CompileContext, server: &ServerConfig) -> Permissions {
+        Permissions {
+            net: vec![format!("{}:{}", server.host, server.port)],
+            read: vec![
+                compile_context.output_dir.clone(),
+                compile_context.routes_path.clone(),
+                compile_context.views_path.clone(),
+                compile_context.static_path.clone(),
+            ],
+            ..Default::default()
+        }
+    }
+
+    pub fn net(mut self, host: impl Into<String>) -> Permissions {
+        self.net.push(host.into());
+        self
+    }
+
+    pub fn read(mut self, path: impl Into<String>) -> Permissions {
+        self.read.push(path.into());
+        self
+    }
+
+    pub fn write(mut self, path: impl Into<String>) -> Permissions {
+        self.write.push(path.into());
+        self
+    }
+
+    pub fn env(mut self, name: impl Into<String>) -> Permissions {
+        self.env.push(name.into());
+        self
+    }
+
+    /// Add the permissions declared by the project, the paths must be
+    /// already resolved.
+    pub fn extend(mut self, section: &PermissionsSection) -> Permissions {
+        self.net.extend(section.net.iter().cloned());
+        self.read.extend(section.read.iter().cloned());
+        self.write.extend(section.write.iter().cloned());
+        self.env.extend(section.env.iter().cloned());
+        self.all |= section.all;
+        self
+    }
+
+    /// Flags for `deno run`
+    pub fn to_flags(&self) -> Vec<String> {
+        if self.all {
+            return vec!["-A".to_owned()];
+        }
+
+        let mut flags = Vec::new();
+        for (flag, values) in [
+            ("net", &self.net),
+            ("read", &self.read),
+            ("write", &self.write),
+            ("env", &self.env),
+        ] {
+            let mut seen = HashSet::new();
+            let values: Vec<&str> = values
+                .iter()
+                .map(String::as_str)
+                .filter(|value| seen.insert(*value))
+                .collect();
+            if !values.is_empty() {
+                flags.push(format!("--allow-{flag}={}", values.join(",")));
+            }
+        }
+
+        flags
+    }
+}
@@ -0,0 +1,164 @@
+//! Dev protocol: messages sent by `densky dev` to the Deno dev server
+//! (`densky/dev.ts`) after each batch of changes.
+//!
+//! The messages are delivered with Server-Sent Events (see
+//! [`crate::channel::DevChannel`]). The url of the endpoint is passed to the
+//! server with the `DENSKY_DEV_CHANNEL` environment variable. Each message is
+//! an `update` event whose `id` is the `buildId`; reconnecting with the
+//! `Last-Event-ID` header (or `?since=<buildId>`) replays the missed ones.
+//!
+//! Every message is a JSON object:
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "buildId": 4,
+//!   "changes": [
+//!     { "kind": "modify", "path": "src/routes/users/[id].ts" },
+//!     { "kind": "create", "path": "src/views/profile.html" },
+//!     { "kind": "rename", "path": "src/routes/me.ts", "from": "src/routes/user.ts" }
+//!   ],
+//!   "affectedRoutes": ["/users/[id]"],
+//!   "fullReload": false,
+//!   "errors": [
+//!     {
+//!       "file": "src/routes/users/[id].ts",
+//!       "phase": "generate",
+//!       "message": "..."
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! - `version`: [`PROTOCOL_VERSION`]. It's increased only on breaking changes
+//!   (removed or renamed fields, new meaning of a value). New fields can be
+//!   added without a new version, so the runtime must ignore unknown fields.
+//! - `buildId`: increases by one on each message of the same `densky dev`
+//!   session, it's reset when the CLI restarts.
+//! - `changes[].kind`: `"create"`, `"modify"`, `"remove"` or `"rename"`.
+//!   Unknown kinds must be handled as `"modify"`.
+//! - `changes[].from`: only on `"rename"`, the previous path of the file.
+//! - `changes[].path`, `changes[].from` and `errors[].file`: relative to the project root and
+//!   always with `/` as separator. Non UTF-8 characters are replaced by `�`.
+//! - `affectedRoutes`: url paths of the routes whose output was regenerated.
+//! - `fullReload`: the whole project was compiled again (e.g. `SIGHUP` to the
+//!   CLI), every module must be reloaded. `changes` is empty in that case.
+//! - `errors`: compile errors of this build, empty if it was successful.
+//!   `phase` is one of `"generate"`, `"write"` or `"manifest"`.
+
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::{
+    compiler::CompileError,
+    watcher::{WatchEvent, WatchKind},
+};
+
+pub const PROTOCOL_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Create,
+    Modify,
+    Remove,
+    Rename,
+}
+
+impl From<&WatchKind> for ChangeKind {
+    fn from(kind: &WatchKind) -> Self {
+        match kind {
+            WatchKind::Create => ChangeKind::Create,
+            WatchKind::Modify => ChangeKind::Modify,
+            WatchKind::Remove => ChangeKind::Remove,
+            WatchKind::Rename { .. } => ChangeKind::Rename,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Change {
+    pub kind: ChangeKind,
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ErrorMessage {
+    pub file: String,
+    pub phase: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DevMessage {
+    pub version: u32,
+    pub build_id: u64,
+    pub changes: Vec<Change>,
+    pub affected_routes: Vec<String>,
+    pub full_reload: bool,
+    pub errors: Vec<ErrorMessage>,
+}
+
+impl DevMessage {
+    pub fn new(
+        root: &Path,
+        build_id: u64,
+        events: &[WatchEvent],
+        affected_routes: Vec<String>,
+        errors: &[CompileError],
+    ) -> DevMessage {
+        DevMessage {
+            version: PROTOCOL_VERSION,
+            build_id,
+            changes: events
+                .iter()
+                .map(|event| Change {
+                    kind: (&event.kind).into(),
+                    path: relative_path(root, &event.path),
+                    from: match &event.kind {
+                        WatchKind::Rename { from } => Some(relative_path(root, from)),
+                        _ => None,
+                    },
+                })
+                .collect(),
+            affected_routes,
+            full_reload: false,
+            errors: errors
+                .iter()
+                .map(|error| ErrorMessage {
+                    file: relative_path(root, &error.file),
+                    phase: error.phase.to_string(),
+                    message: error.message.clone(),
+                })
+                .collect(),
+        }
+    }
+
+    pub fn full_reload(root: &Path, build_id: u64, errors: &[CompileError]) -> DevMessage {
+        DevMessage {
+            full_reload: true,
+            ..DevMessage::new(root, build_id, &[], Vec::new(), errors)
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        // Only strings, numbers and lists, it can't fail
+        serde_json::to_string(self).unwrap()
+    }
+}
+
+/// Path relative to `root` with `/` as separator
+pub fn relative_path(root: &Path, path: &Path) -> String {
+    match path.strip_prefix(root) {
+        Ok(path) => path
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/"),
+        Err(_) => path.to_string_lossy().replace('\\', "/"),
+    }
+}
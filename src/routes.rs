@@ -0,0 +1,283 @@
+//! Flat view of the discovered HTTP tree, one entry per route with a
+//! handler. Used by `densky routes` and the other commands that inspect the
+//! routes without compiling them.
+
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use ahash::HashMap;
+use densky_core::walker::{WalkerContainer, WalkerTree};
+use serde::Serialize;
+
+use crate::protocol::relative_path;
+
+/// Methods that a route file can export, `ANY` matches every method.
+pub static HTTP_METHODS: [&str; 8] = [
+    "GET", "POST", "PUT", "PATCH", "DELETE", "HEAD", "OPTIONS", "ANY",
+];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Route {
+    /// Url path pattern, e.g. `/users/$id`
+    pub path: String,
+    pub params: Vec<String>,
+    pub methods: Vec<String>,
+    /// Source files relative to the project root
+    pub source: String,
+    /// From the outermost to the innermost
+    pub middlewares: Vec<String>,
+    /// The fallback that handles the unmatched requests under this route, it
+    /// can be inherited from a parent.
+    pub fallback: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(transparent)]
+pub struct RouteTable {
+    pub routes: Vec<Route>,
+}
+
+/// Files inherited by the children while walking the tree
+#[derive(Debug, Default, Clone)]
+struct Inherited {
+    middlewares: Vec<PathBuf>,
+    fallback: Option<PathBuf>,
+}
+
+impl RouteTable {
+    /// `root` is the project folder, the paths of the table are relative to it
+    pub fn build(
+        http_tree: Arc<Mutex<WalkerTree>>,
+        container: &WalkerContainer,
+        root: &Path,
+    ) -> RouteTable {
+        let mut table = RouteTable::default();
+        table.walk(http_tree, container, root, Inherited::default());
+        table.routes.sort_by(|a, b| a.path.cmp(&b.path));
+        table
+    }
+
+    fn walk(
+        &mut self,
+        http_tree: Arc<Mutex<WalkerTree>>,
+        container: &WalkerContainer,
+        root: &Path,
+        mut inherited: Inherited,
+    ) {
+        let tree = http_tree.lock().unwrap();
+        let file_path = |leaf: Option<usize>| {
+            leaf.and_then(|leaf| container.get_leaf(leaf))
+                .map(|leaf| leaf.lock().unwrap().file_path.clone())
+        };
+
+        if let Some(middleware) = file_path(tree.middleware) {
+            inherited.middlewares.push(middleware);
+        }
+        if let Some(fallback) = file_path(tree.fallback) {
+            inherited.fallback = Some(fallback);
+        }
+
+        if let Some(source) = file_path(tree.leaf) {
+            self.routes.push(Route {
+                path: tree.path.clone(),
+                params: path_params(&tree.path),
+                methods: exported_methods(&source),
+                source: relative_path(root, &source),
+                middlewares: inherited
+                    .middlewares
+                    .iter()
+                    .map(|path| relative_path(root, path))
+                    .collect(),
+                fallback: inherited
+                    .fallback
+                    .as_ref()
+                    .map(|path| relative_path(root, path)),
+            });
+        }
+
+        let children = tree.children.clone();
+        drop(tree);
+
+        for child in children {
+            if let Some(child) = container.get_tree(child) {
+                self.walk(child, container, root, inherited.clone());
+            }
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        // Only strings and lists, it can't fail
+        serde_json::to_string_pretty(self).unwrap()
+    }
+}
+
+impl fmt::Display for RouteTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let header = [
+            "METHOD",
+            "PATH",
+            "PARAMS",
+            "SOURCE",
+            "MIDDLEWARES",
+            "FALLBACK",
+        ];
+        let rows: Vec<[String; 6]> = self
+            .routes
+            .iter()
+            .map(|route| {
+                [
+                    route.methods.join(","),
+                    route.path.clone(),
+                    route.params.join(","),
+                    route.source.clone(),
+                    route.middlewares.join(" > "),
+                    route.fallback.clone().unwrap_or_default(),
+                ]
+            })
+            .collect();
+
+        let mut widths = header.map(str::len);
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+
+        let write_row = |f: &mut fmt::Formatter<'_>, cells: &[&str]| {
+            let line = cells
+                .iter()
+                .zip(widths)
+                .map(|(cell, width)| format!("{cell:width$}"))
+                .collect::<Vec<_>>()
+                .join("  ");
+            writeln!(f, "{}", line.trim_end())
+        };
+
+        write_row(f, &header)?;
+        for row in &rows {
+            write_row(f, &row.each_ref().map(String::as_str))?;
+        }
+        write!(f, "{} routes", rows.len())
+    }
+}
+
+/// `ANY` is every method, the others are themselves
+pub fn expand_method(method: &str) -> Vec<&str> {
+    if method == "ANY" {
+        HTTP_METHODS
+            .iter()
+            .copied()
+            .filter(|method| *method != "ANY")
+            .collect()
+    } else {
+        vec![method]
+    }
+}
+
+/// camelCase name of the handler of a method on a path, e.g. `GET
+/// /users/$id` is `getUsersById`. `method` must be lowercase.
+pub fn operation_id(method: &str, path: &str) -> String {
+    let mut id = method.to_owned();
+    for segment in path.split('/') {
+        let segment = match dynamic_segment(segment) {
+            Some(name) => {
+                id.push_str("By");
+                name
+            }
+            None => segment,
+        };
+        for word in segment.split(|c: char| !c.is_ascii_alphanumeric()) {
+            let mut chars = word.chars();
+            if let Some(first) = chars.next() {
+                id.push(first.to_ascii_uppercase());
+                id.extend(chars);
+            }
+        }
+    }
+    id
+}
+
+/// Unique [`operation_id`]s of the routes of a table, shared by the client
+/// and the OpenAPI document so both use the same names.
+#[derive(Debug, Default)]
+pub struct OperationIds {
+    /// Name -> path of the route that uses it
+    names: HashMap<String, String>,
+}
+
+impl OperationIds {
+    /// Name of a method on a path, `method` must be lowercase. A name taken
+    /// by another path (`/users/$id` and `/users/by-id`) is numbered in the
+    /// order of the calls.
+    ///
+    /// Returns `None` if the path already has the method, e.g. when it's
+    /// exported twice (`GET` and `ANY`) the first one is kept.
+    pub fn assign(&mut self, method: &str, path: &str) -> Option<String> {
+        let mut name = operation_id(method, path);
+        match self.names.get(&name) {
+            Some(owner) if owner == path => return None,
+            Some(_) => {
+                let base = name;
+                name = (2..)
+                    .map(|n| format!("{base}{n}"))
+                    .find(|name| !self.names.contains_key(name))
+                    .unwrap();
+            }
+            None => (),
+        }
+        self.names.insert(name.clone(), path.to_owned());
+        Some(name)
+    }
+}
+
+/// Name of the param if the segment is dynamic (`$id` or `[id]`)
+pub fn dynamic_segment(segment: &str) -> Option<&str> {
+    segment.strip_prefix('$').or_else(|| {
+        segment
+            .strip_prefix('[')
+            .and_then(|segment| segment.strip_suffix(']'))
+    })
+}
+
+pub fn path_params(path: &str) -> Vec<String> {
+    path.split('/')
+        .filter_map(dynamic_segment)
+        .map(str::to_owned)
+        .collect()
+}
+
+/// Method exported by the line, if it's an export of one (`export function
+/// GET`, `export async function POST`, `export const PUT = ...`)
+pub fn exported_method(line: &str) -> Option<&'static str> {
+    let line = line.trim_start().strip_prefix("export ")?.trim_start();
+    let line = line.strip_prefix("async ").unwrap_or(line).trim_start();
+    let name = ["function ", "const ", "let "]
+        .iter()
+        .find_map(|keyword| line.strip_prefix(keyword))?
+        .trim_start();
+    let name = name
+        .split(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+        .next()?;
+    HTTP_METHODS.iter().find(|method| **method == name).copied()
+}
+
+/// Methods exported by the route file, found by scanning the source. An
+/// unreadable file has no methods.
+pub fn exported_methods(file_path: &Path) -> Vec<String> {
+    let Ok(source) = fs::read_to_string(file_path) else {
+        return Vec::new();
+    };
+
+    let mut methods: Vec<String> = source
+        .lines()
+        .filter_map(exported_method)
+        .map(str::to_owned)
+        .collect();
+
+    methods.sort_by_key(|method| HTTP_METHODS.iter().position(|m| m == method));
+    methods.dedup();
+    methods
+}
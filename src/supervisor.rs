@@ -0,0 +1,203 @@
+use std::{
+    ffi::OsString,
+    io,
+    process::{Child, Command},
+    thread,
+    time::{Duration, Instant},
+};
+
+static MIN_BACKOFF: Duration = Duration::from_millis(250);
+static MAX_BACKOFF: Duration = Duration::from_secs(10);
+/// A process that lives this long is considered healthy, so its crashes
+/// don't increase the backoff anymore.
+static HEALTHY_UPTIME: Duration = Duration::from_secs(10);
+/// Time given to the process to exit after SIGTERM, then it's killed
+pub static STOP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Keeps a child process alive: it detects when the process exits and
+/// restarts it on the next change, waiting more after each crash.
+#[derive(Debug)]
+pub struct Supervisor {
+    program: OsString,
+    args: Vec<OsString>,
+    envs: Vec<(OsString, OsString)>,
+    child: Option<Child>,
+    started_at: Instant,
+    /// Crashes in a row, used to compute the backoff
+    crashes: u32,
+    crashed_at: Option<Instant>,
+    /// A change arrived while waiting the backoff
+    pending_restart: bool,
+}
+
+impl Supervisor {
+    pub fn new<I, S>(program: impl Into<OsString>, args: I) -> Supervisor
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<OsString>,
+    {
+        Supervisor {
+            program: program.into(),
+            args: args.into_iter().map(Into::into).collect(),
+            envs: Vec::new(),
+            child: None,
+            started_at: Instant::now(),
+            crashes: 0,
+            crashed_at: None,
+            pending_restart: false,
+        }
+    }
+
+    pub fn args<I, S>(mut self, args: I) -> Supervisor
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<OsString>,
+    {
+        self.args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    /// Environment variable for the process, it's kept on restarts.
+    pub fn env(mut self, key: impl Into<OsString>, value: impl Into<OsString>) -> Supervisor {
+        self.envs.push((key.into(), value.into()));
+        self
+    }
+
+    /// Change an environment variable, it's used from the next restart.
+    pub fn set_env(&mut self, key: impl Into<OsString>, value: impl Into<OsString>) {
+        let key = key.into();
+        let value = value.into();
+        match self.envs.iter_mut().find(|(name, _)| *name == key) {
+            Some((_, old)) => *old = value,
+            None => self.envs.push((key, value)),
+        }
+    }
+
+    pub fn spawn(mut self) -> io::Result<Supervisor> {
+        self.start()?;
+        Ok(self)
+    }
+
+    fn start(&mut self) -> io::Result<()> {
+        let child = Command::new(&self.program)
+            .args(&self.args)
+            .envs(self.envs.iter().map(|(key, value)| (key, value)))
+            .spawn()?;
+        self.child = Some(child);
+        self.started_at = Instant::now();
+        self.pending_restart = false;
+
+        Ok(())
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.child.is_some()
+    }
+
+    fn backoff(&self) -> Duration {
+        let backoff = MIN_BACKOFF * 2u32.saturating_pow(self.crashes.saturating_sub(1));
+        backoff.min(MAX_BACKOFF)
+    }
+
+    /// Check if the process exited and restart it if a change is waiting for
+    /// the backoff. Must be called periodically.
+    pub fn tick(&mut self) {
+        if let Some(child) = &mut self.child {
+            match child.try_wait() {
+                Ok(Some(status)) => {
+                    self.child = None;
+                    if self.started_at.elapsed() >= HEALTHY_UPTIME {
+                        self.crashes = 0;
+                    }
+                    self.crashes += 1;
+                    self.crashed_at = Some(Instant::now());
+                    eprintln!(
+                        "[Supervisor] {} exited ({status}), waiting for changes to restart",
+                        self.program.to_string_lossy()
+                    );
+                }
+                Ok(None) => (),
+                Err(err) => eprintln!("[Supervisor] {err}"),
+            }
+        }
+
+        if self.pending_restart && self.can_restart() {
+            self.restart();
+        }
+    }
+
+    fn can_restart(&self) -> bool {
+        match self.crashed_at {
+            Some(crashed_at) => crashed_at.elapsed() >= self.backoff(),
+            None => true,
+        }
+    }
+
+    /// Notify a change in the project, a dead process is restarted (now or
+    /// after the backoff).
+    pub fn on_change(&mut self) {
+        if self.is_running() {
+            return;
+        }
+
+        if self.can_restart() {
+            self.restart();
+        } else {
+            self.pending_restart = true;
+        }
+    }
+
+    /// Stop the process (if running) and start it again.
+    pub fn restart(&mut self) {
+        self.stop(STOP_TIMEOUT);
+
+        eprintln!("[Supervisor] Restarting {}", self.program.to_string_lossy());
+        if let Err(err) = self.start() {
+            eprintln!("[Supervisor] Can't restart: {err}");
+            self.crashes += 1;
+            self.crashed_at = Some(Instant::now());
+        }
+    }
+
+    /// Ask the process to exit (SIGTERM) and wait for it, it's killed if
+    /// it's still running after the timeout. On non-unix platforms it's
+    /// killed right away.
+    pub fn stop(&mut self, timeout: Duration) {
+        let Some(mut child) = self.child.take() else {
+            return;
+        };
+
+        #[cfg(unix)]
+        {
+            // SAFETY: `kill` doesn't touch memory, the pid is from our child
+            // that wasn't waited yet, so it can't be reused by another process.
+            unsafe {
+                libc::kill(child.id() as libc::pid_t, libc::SIGTERM);
+            }
+
+            let started = Instant::now();
+            while started.elapsed() < timeout {
+                match child.try_wait() {
+                    Ok(Some(_)) | Err(_) => return,
+                    Ok(None) => thread::sleep(Duration::from_millis(50)),
+                }
+            }
+
+            eprintln!(
+                "[Supervisor] {} didn't exit after {timeout:?}, killing it",
+                self.program.to_string_lossy()
+            );
+        }
+        #[cfg(not(unix))]
+        let _ = timeout;
+
+        let _ = child.kill(); // Err(): Command wasn't running
+        let _ = child.wait();
+    }
+}
+
+impl Drop for Supervisor {
+    fn drop(&mut self) {
+        self.stop(STOP_TIMEOUT);
+    }
+}
@@ -0,0 +1,57 @@
+/// A file embedded in the binary, `path` is relative to the project root.
+pub struct TemplateFile {
+    pub path: &'static str,
+    pub content: &'static str,
+}
+
+macro_rules! template_file {
+    ($path:literal, $source:literal) => {
+        TemplateFile {
+            path: $path,
+            content: include_str!(concat!("../templates/", $source)),
+        }
+    };
+}
+
+static COMMON: &[TemplateFile] = &[
+    template_file!("deno.json", "common/deno.json"),
+    template_file!("config.ts", "common/config.ts"),
+    template_file!("densky.toml", "common/densky.toml"),
+    template_file!(".gitignore", "common/gitignore"),
+    template_file!("src/routes/_middleware.ts", "common/_middleware.ts"),
+    template_file!("src/routes/_fallback.ts", "common/_fallback.ts"),
+    template_file!("src/views/index.html", "common/index.html"),
+];
+
+static MINIMAL: &[TemplateFile] = &[template_file!(
+    "src/routes/index.ts",
+    "minimal/src/routes/index.ts"
+)];
+
+static VIEWS: &[TemplateFile] = &[template_file!(
+    "src/routes/index.ts",
+    "views/src/routes/index.ts"
+)];
+
+static STATIC: &[TemplateFile] = &[
+    template_file!("src/routes/index.ts", "static/src/routes/index.ts"),
+    template_file!("src/static/index.html", "static/src/static/index.html"),
+    template_file!("src/static/style.css", "static/src/static/style.css"),
+];
+
+/// Directories that must exist even if the template doesn't put files on it,
+/// they are the ones expected by the default `CompileContext`.
+pub static PROJECT_DIRS: &[&str] = &["src/routes", "src/views", "src/static"];
+
+pub static TEMPLATE_NAMES: [&str; 3] = ["minimal", "views", "static"];
+
+pub fn get_template(name: &str) -> Option<impl Iterator<Item = &'static TemplateFile>> {
+    let files = match name {
+        "minimal" => MINIMAL,
+        "views" => VIEWS,
+        "static" => STATIC,
+        _ => return None,
+    };
+
+    Some(COMMON.iter().chain(files.iter()))
+}
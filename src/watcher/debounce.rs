@@ -0,0 +1,382 @@
+use std::{
+    ffi::OsStr,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use ahash::HashMap;
+
+use super::{WatchEvent, WatchKind, WatchRoot, Watcher};
+
+/// Wraps a watcher to report the changes in batches: the events are kept
+/// until nothing changes for the quiet window, so a save storm or a
+/// `git checkout` produces a single batch.
+pub struct Debouncer {
+    watcher: Box<dyn Watcher>,
+    quiet: Duration,
+    pending: EventBatch,
+    first_event: Option<Instant>,
+    last_event: Option<Instant>,
+}
+
+impl Debouncer {
+    pub fn new(watcher: Box<dyn Watcher>, quiet: Duration) -> Debouncer {
+        Debouncer {
+            watcher,
+            quiet,
+            pending: EventBatch::default(),
+            first_event: None,
+            last_event: None,
+        }
+    }
+
+    /// Changes that never stop (e.g. a log file) flush the batch after this
+    fn max_wait(&self) -> Duration {
+        self.quiet * 10
+    }
+}
+
+impl Watcher for Debouncer {
+    fn poll(&mut self) -> Vec<WatchEvent> {
+        let events = self.watcher.poll();
+        if !events.is_empty() {
+            let now = Instant::now();
+            self.first_event.get_or_insert(now);
+            self.last_event = Some(now);
+            for event in events {
+                self.pending.push(event);
+            }
+        }
+
+        let (Some(first_event), Some(last_event)) = (self.first_event, self.last_event) else {
+            return Vec::new();
+        };
+        if last_event.elapsed() < self.quiet && first_event.elapsed() < self.max_wait() {
+            return Vec::new();
+        }
+
+        self.first_event = None;
+        self.last_event = None;
+        let events = std::mem::take(&mut self.pending).into_events();
+        if self.watcher.exact_renames() {
+            events
+        } else {
+            detect_renames(events)
+        }
+    }
+
+    fn exact_renames(&self) -> bool {
+        self.watcher.exact_renames()
+    }
+}
+
+/// Batch of changes with only one event per file, indexed by path:
+///
+/// - create + modify = create
+/// - create + remove = nothing
+/// - remove + create = modify
+/// - create + rename = create of the new path
+/// - rename + modify = rename
+/// - rename + remove = remove of the old path
+/// - otherwise the last one wins
+#[derive(Debug, Default)]
+pub struct EventBatch {
+    /// `None` when a create + remove cancelled the event
+    events: Vec<Option<WatchEvent>>,
+    index: HashMap<PathBuf, usize>,
+}
+
+impl EventBatch {
+    pub fn push(&mut self, mut event: WatchEvent) {
+        use WatchKind::*;
+
+        // The pending event of the old path follows the file
+        if let Rename { from } = &event.kind {
+            if let Some(index) = self.index.remove(from) {
+                let pending = self.events[index].take();
+                match pending.map(|pending| pending.kind) {
+                    Some(Create) => event.kind = Create,
+                    Some(Rename { from }) if from == event.path => event.kind = Modify,
+                    Some(Rename { from }) => event.kind = Rename { from },
+                    _ => (),
+                }
+            }
+        }
+
+        let Some(&index) = self.index.get(&event.path) else {
+            self.index.insert(event.path.clone(), self.events.len());
+            self.events.push(Some(event));
+            return;
+        };
+        let Some(pending) = &mut self.events[index] else {
+            unreachable!("cancelled events aren't indexed");
+        };
+
+        match (&pending.kind, event.kind) {
+            (Create, Modify) | (Rename { .. }, Modify) => (),
+            (Create, Remove) => {
+                self.events[index] = None;
+                self.index.remove(&event.path);
+            }
+            (Rename { from }, Remove) => {
+                let from = from.clone();
+                self.events[index] = None;
+                self.index.remove(&event.path);
+                self.push_removed(from, event.root);
+            }
+            (Remove, Create) => pending.kind = Modify,
+            (_, kind) => pending.kind = kind,
+        }
+    }
+
+    /// A renamed file was removed, so it's the old path that's gone. A new
+    /// file may already be pending there.
+    fn push_removed(&mut self, path: PathBuf, root: WatchRoot) {
+        if let Some(pending) = self
+            .index
+            .get(&path)
+            .and_then(|&index| self.events[index].as_mut())
+        {
+            if matches!(pending.kind, WatchKind::Create) {
+                pending.kind = WatchKind::Modify;
+            }
+            return;
+        }
+
+        self.push(WatchEvent {
+            kind: WatchKind::Remove,
+            path,
+            root,
+        });
+    }
+
+    pub fn into_events(self) -> Vec<WatchEvent> {
+        self.events.into_iter().flatten().collect()
+    }
+}
+
+/// A remove and a create of the same root are a rename when the file kept
+/// its name in another folder, or it kept its folder and extension. Only
+/// unambiguous pairs are joined, when several files could match they stay
+/// as remove + create.
+fn is_rename(from: &Path, to: &Path) -> bool {
+    let same_name = from.file_name() == to.file_name();
+    let same_dir = from.parent() == to.parent();
+    let same_extension = from.extension() == to.extension();
+    same_name != same_dir && (same_name || same_extension)
+}
+
+/// Root, folder and extension of a path
+type DirKey<'a> = (WatchRoot, Option<&'a Path>, Option<&'a OsStr>);
+
+/// Events that could be the other half of a rename, grouped by the
+/// parts of the path that [`is_rename`] compares.
+#[derive(Default)]
+struct RenameIndex<'a> {
+    by_name: HashMap<(WatchRoot, Option<&'a OsStr>), Vec<usize>>,
+    by_dir: HashMap<DirKey<'a>, Vec<usize>>,
+}
+
+impl<'a> RenameIndex<'a> {
+    fn insert(&mut self, index: usize, event: &'a WatchEvent) {
+        let path = &event.path;
+        self.by_name
+            .entry((event.root, path.file_name()))
+            .or_default()
+            .push(index);
+        self.by_dir
+            .entry((event.root, path.parent(), path.extension()))
+            .or_default()
+            .push(index);
+    }
+
+    /// The indexed events that `event` could be renamed from or to
+    fn candidates(&self, events: &[WatchEvent], event: &WatchEvent) -> Vec<usize> {
+        let path = &event.path;
+        let by_name = self.by_name.get(&(event.root, path.file_name()));
+        let by_dir = self
+            .by_dir
+            .get(&(event.root, path.parent(), path.extension()));
+        by_name
+            .into_iter()
+            .chain(by_dir)
+            .flatten()
+            .copied()
+            .filter(|&other| is_rename(path, &events[other].path))
+            .collect()
+    }
+}
+
+fn detect_renames(events: Vec<WatchEvent>) -> Vec<WatchEvent> {
+    let mut creates = RenameIndex::default();
+    let mut removes = RenameIndex::default();
+    for (index, event) in events.iter().enumerate() {
+        match event.kind {
+            WatchKind::Create => creates.insert(index, event),
+            WatchKind::Remove => removes.insert(index, event),
+            _ => (),
+        }
+    }
+
+    // create index -> remove index
+    let mut renames = Vec::new();
+    for (index, event) in events.iter().enumerate() {
+        if !matches!(event.kind, WatchKind::Remove) {
+            continue;
+        }
+        if let [create] = creates.candidates(&events, event)[..] {
+            if let [_] = removes.candidates(&events, &events[create])[..] {
+                renames.push((create, index));
+            }
+        }
+    }
+
+    let mut events: Vec<Option<WatchEvent>> = events.into_iter().map(Some).collect();
+    for (create, remove) in renames {
+        let from = events[remove].take().unwrap().path;
+        if let Some(event) = &mut events[create] {
+            event.kind = WatchKind::Rename { from };
+        }
+    }
+
+    events.into_iter().flatten().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(kind: WatchKind, path: &str) -> WatchEvent {
+        WatchEvent {
+            kind,
+            path: PathBuf::from(path),
+            root: WatchRoot::Routes,
+        }
+    }
+
+    fn batch(events: impl IntoIterator<Item = WatchEvent>) -> Vec<WatchEvent> {
+        let mut batch = EventBatch::default();
+        for event in events {
+            batch.push(event);
+        }
+        detect_renames(batch.into_events())
+    }
+
+    fn rename(from: &str, to: &str) -> WatchEvent {
+        event(
+            WatchKind::Rename {
+                from: PathBuf::from(from),
+            },
+            to,
+        )
+    }
+
+    #[test]
+    fn create_and_modify_is_create() {
+        let events = batch([
+            event(WatchKind::Create, "/routes/index.ts"),
+            event(WatchKind::Modify, "/routes/index.ts"),
+        ]);
+        assert_eq!(events.len(), 1);
+        assert!(matches!(events[0].kind, WatchKind::Create));
+    }
+
+    #[test]
+    fn create_and_remove_is_nothing() {
+        let events = batch([
+            event(WatchKind::Create, "/routes/index.ts"),
+            event(WatchKind::Modify, "/routes/index.ts"),
+            event(WatchKind::Remove, "/routes/index.ts"),
+        ]);
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn remove_and_create_of_the_same_file_is_modify() {
+        let events = batch([
+            event(WatchKind::Remove, "/routes/index.ts"),
+            event(WatchKind::Create, "/routes/index.ts"),
+        ]);
+        assert_eq!(events.len(), 1);
+        assert!(matches!(events[0].kind, WatchKind::Modify));
+    }
+
+    #[test]
+    fn remove_and_create_is_rename() {
+        let events = batch([
+            event(WatchKind::Remove, "/routes/users.ts"),
+            event(WatchKind::Create, "/routes/accounts.ts"),
+            event(WatchKind::Remove, "/routes/api/index.ts"),
+            event(WatchKind::Create, "/routes/v1/index.ts"),
+        ]);
+        assert_eq!(events.len(), 2);
+        let WatchKind::Rename { from } = &events[0].kind else {
+            panic!("expected a rename, got {:?}", events[0]);
+        };
+        assert_eq!(events[0].path, PathBuf::from("/routes/accounts.ts"));
+        assert_eq!(from, &PathBuf::from("/routes/users.ts"));
+        let WatchKind::Rename { from } = &events[1].kind else {
+            panic!("expected a rename, got {:?}", events[1]);
+        };
+        assert_eq!(events[1].path, PathBuf::from("/routes/v1/index.ts"));
+        assert_eq!(from, &PathBuf::from("/routes/api/index.ts"));
+    }
+
+    #[test]
+    fn ambiguous_renames_are_kept() {
+        let events = batch([
+            event(WatchKind::Remove, "/routes/a.ts"),
+            event(WatchKind::Create, "/routes/b.ts"),
+            event(WatchKind::Create, "/routes/c.ts"),
+        ]);
+        assert_eq!(events.len(), 3);
+        assert!(events
+            .iter()
+            .all(|event| !matches!(event.kind, WatchKind::Rename { .. })));
+    }
+
+    #[test]
+    fn renames_stay_in_their_root() {
+        let mut create = event(WatchKind::Create, "/views/users.ts");
+        create.root = WatchRoot::Views;
+        let events = batch([event(WatchKind::Remove, "/routes/users.ts"), create]);
+        assert_eq!(events.len(), 2);
+    }
+
+    #[test]
+    fn created_then_renamed_is_create() {
+        let events = batch([
+            event(WatchKind::Create, "/routes/users.ts"),
+            rename("/routes/users.ts", "/routes/accounts.ts"),
+        ]);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].path, PathBuf::from("/routes/accounts.ts"));
+        assert!(matches!(events[0].kind, WatchKind::Create));
+    }
+
+    #[test]
+    fn renames_are_chained() {
+        let events = batch([
+            rename("/routes/a.ts", "/routes/b.ts"),
+            event(WatchKind::Modify, "/routes/b.ts"),
+            rename("/routes/b.ts", "/routes/c.ts"),
+        ]);
+        assert_eq!(events.len(), 1);
+        let WatchKind::Rename { from } = &events[0].kind else {
+            panic!("expected a rename, got {:?}", events[0]);
+        };
+        assert_eq!(events[0].path, PathBuf::from("/routes/c.ts"));
+        assert_eq!(from, &PathBuf::from("/routes/a.ts"));
+    }
+
+    #[test]
+    fn renamed_then_removed_is_remove() {
+        let events = batch([
+            rename("/routes/a.ts", "/routes/b.ts"),
+            event(WatchKind::Remove, "/routes/b.ts"),
+        ]);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].path, PathBuf::from("/routes/a.ts"));
+        assert!(matches!(events[0].kind, WatchKind::Remove));
+    }
+}
@@ -0,0 +1,64 @@
+use std::{fs, path::PathBuf, time::SystemTime};
+
+use super::{PathFilter, WatchEvent, WatchKind, WatchRoot, Watcher};
+
+/// Watches a few files that may not exist (e.g. `densky.toml`), it only
+/// reads their metadata on each poll. The files excluded by the ignore rules
+/// aren't watched.
+#[derive(Debug)]
+pub struct FilesWatcher {
+    root: WatchRoot,
+    /// Last modification time and size, `None` if the file doesn't exist
+    files: Vec<(PathBuf, Option<(SystemTime, u64)>)>,
+}
+
+impl FilesWatcher {
+    pub fn new(root: WatchRoot, files: Vec<PathBuf>, filter: &PathFilter) -> FilesWatcher {
+        FilesWatcher {
+            root,
+            files: files
+                .into_iter()
+                .filter(|file| !filter.is_excluded(file, false))
+                .map(|file| {
+                    let state = Self::state(&file);
+                    (file, state)
+                })
+                .collect(),
+        }
+    }
+
+    fn state(path: &PathBuf) -> Option<(SystemTime, u64)> {
+        let metadata = fs::metadata(path).ok()?;
+        Some((metadata.modified().ok()?, metadata.len()))
+    }
+}
+
+impl Watcher for FilesWatcher {
+    fn poll(&mut self) -> Vec<WatchEvent> {
+        let mut events = Vec::new();
+
+        for (path, state) in &mut self.files {
+            let new_state = Self::state(path);
+            let kind = match (&state, &new_state) {
+                (None, Some(_)) => WatchKind::Create,
+                (Some(_), None) => WatchKind::Remove,
+                (Some(old), Some(new)) if old != new => WatchKind::Modify,
+                _ => continue,
+            };
+
+            *state = new_state;
+            events.push(WatchEvent {
+                kind,
+                path: path.clone(),
+                root: self.root,
+            });
+        }
+
+        events
+    }
+
+    fn exact_renames(&self) -> bool {
+        // The config files are fixed, none of them is renamed to another
+        true
+    }
+}
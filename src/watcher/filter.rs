@@ -0,0 +1,98 @@
+use std::path::{Path, PathBuf};
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+
+/// File with the ignore rules of the project, same syntax as `.gitignore`
+pub static IGNORE_FILENAME: &str = ".denskyignore";
+
+/// Always ignored: VCS data, dependencies and editor temporary files
+/// (`~` backups and the `4913` probe file of vim).
+static DEFAULT_IGNORES: [&str; 4] = [".git/", "node_modules/", "*~", "4913"];
+
+/// Decides which files of the project are watched. Built from the
+/// `.gitignore` and `.denskyignore` of the project root, the output dir and
+/// the `include`/`exclude` globs of the config.
+///
+/// Ignored folders are skipped entirely, their content is never read.
+///
+/// Only the ignore files of the project root are loaded, a `.gitignore`
+/// inside a subfolder has no effect; its rules must be moved to the root
+/// `.denskyignore` (with the folder as prefix).
+#[derive(Debug, Clone)]
+pub struct PathFilter {
+    root: PathBuf,
+    ignore: Gitignore,
+    /// When it isn't empty only the files that match it are watched
+    include: Gitignore,
+}
+
+impl PathFilter {
+    /// The globs use the `.gitignore` syntax and are relative to `root`.
+    pub fn new(
+        root: &Path,
+        output_dir: &Path,
+        include: &[String],
+        exclude: &[String],
+    ) -> Result<PathFilter, ignore::Error> {
+        let mut ignore = GitignoreBuilder::new(root);
+        for glob in DEFAULT_IGNORES {
+            ignore.add_line(None, glob)?;
+        }
+        for file in [".gitignore", IGNORE_FILENAME] {
+            let path = root.join(file);
+            if path.is_file() {
+                if let Some(err) = ignore.add(path) {
+                    return Err(err);
+                }
+            }
+        }
+        if let Ok(output_dir) = output_dir.strip_prefix(root) {
+            ignore.add_line(None, &format!("/{}/", output_dir.display()))?;
+        }
+        for glob in exclude {
+            ignore.add_line(None, glob)?;
+        }
+
+        let mut include_builder = GitignoreBuilder::new(root);
+        for glob in include {
+            include_builder.add_line(None, glob)?;
+        }
+
+        Ok(PathFilter {
+            root: root.to_path_buf(),
+            ignore: ignore.build()?,
+            include: include_builder.build()?,
+        })
+    }
+
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        // The matchers panic with paths outside of the root
+        if !path.starts_with(&self.root) || path == self.root {
+            return false;
+        }
+
+        if self.is_excluded(path, is_dir) {
+            return true;
+        }
+
+        // Folders are walked to find the included files inside them
+        !is_dir
+            && !self.include.is_empty()
+            && !self
+                .include
+                .matched_path_or_any_parents(path, false)
+                .is_ignore()
+    }
+
+    /// Only the ignore rules, the `include` globs select the sources so they
+    /// don't apply to the config files.
+    pub fn is_excluded(&self, path: &Path, is_dir: bool) -> bool {
+        if !path.starts_with(&self.root) || path == self.root {
+            return false;
+        }
+
+        self.ignore
+            .matched_path_or_any_parents(path, is_dir)
+            .is_ignore()
+    }
+}
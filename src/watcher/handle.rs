@@ -0,0 +1,68 @@
+use std::{
+    io,
+    sync::mpsc::{self, Receiver, RecvError, RecvTimeoutError, Sender},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use super::{WatchEvent, Watcher};
+
+/// Runs a watcher on its own thread, polling it every `interval`. The
+/// batches of events are delivered over a channel, and the thread is stopped
+/// and joined when the handle is dropped.
+pub struct WatcherHandle {
+    events: Receiver<Vec<WatchEvent>>,
+    shutdown: Option<Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl WatcherHandle {
+    pub fn spawn(mut watcher: Box<dyn Watcher>, interval: Duration) -> io::Result<WatcherHandle> {
+        let (events_tx, events) = mpsc::channel();
+        let (shutdown, shutdown_rx) = mpsc::channel::<()>();
+
+        let thread = thread::Builder::new()
+            .name("watcher".to_owned())
+            .spawn(move || {
+                // Anything else than a timeout is the shutdown or the handle
+                // was dropped
+                while let Err(RecvTimeoutError::Timeout) = shutdown_rx.recv_timeout(interval) {
+                    let batch = watcher.poll();
+                    if !batch.is_empty() && events_tx.send(batch).is_err() {
+                        break;
+                    }
+                }
+            })?;
+
+        Ok(WatcherHandle {
+            events,
+            shutdown: Some(shutdown),
+            thread: Some(thread),
+        })
+    }
+
+    /// Wait up to `timeout` for changes. All the batches that are already
+    /// waiting are returned together.
+    ///
+    /// Returns `Err` if the thread stopped (e.g. the watcher panicked), no
+    /// more changes will be delivered.
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<Vec<WatchEvent>, RecvError> {
+        let mut events = match self.events.recv_timeout(timeout) {
+            Ok(events) => events,
+            Err(RecvTimeoutError::Timeout) => return Ok(Vec::new()),
+            Err(RecvTimeoutError::Disconnected) => return Err(RecvError),
+        };
+        events.extend(self.events.try_iter().flatten());
+        Ok(events)
+    }
+}
+
+impl Drop for WatcherHandle {
+    fn drop(&mut self) {
+        // Closing the channel stops the thread
+        drop(self.shutdown.take());
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
@@ -1,10 +1,246 @@
+mod debounce;
+mod files;
+mod filter;
+mod handle;
+#[cfg(target_os = "linux")]
+mod native;
 mod poll;
 mod utils;
 
+pub use debounce::*;
+pub use files::*;
+pub use filter::*;
+pub use handle::*;
+#[cfg(target_os = "linux")]
+pub use native::*;
 pub use poll::*;
+pub use utils::DirIterator;
+
+use utils::walk_dir;
+
+use std::{io, path::PathBuf};
 
 use ahash::RandomState;
 
 thread_local! {
     pub static MAIN_HASHER: RandomState = RandomState::with_seed(29384);
 }
+
+#[derive(Debug, Clone)]
+pub enum WatchKind {
+    Create,
+    Remove,
+    Modify,
+    /// The file was moved from `from` to the path of the event.
+    Rename {
+        from: PathBuf,
+    },
+}
+
+/// Source folder of the project that produced an event
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WatchRoot {
+    Routes,
+    Views,
+    Static,
+    /// The entry and config files of the project root (`densky.toml`,
+    /// `main.ts`, `config.ts`, `deno.json`)
+    Config,
+}
+
+#[derive(Debug, Clone)]
+pub struct WatchEvent {
+    pub kind: WatchKind,
+    pub path: PathBuf,
+    pub root: WatchRoot,
+}
+
+/// Same events with each rename replaced by a remove and a create, for the
+/// consumers that handle both in the same way.
+pub fn split_renames(events: &[WatchEvent]) -> Vec<WatchEvent> {
+    let mut split = Vec::with_capacity(events.len());
+    for event in events {
+        match &event.kind {
+            WatchKind::Rename { from } => {
+                split.push(WatchEvent {
+                    kind: WatchKind::Remove,
+                    path: from.clone(),
+                    root: event.root,
+                });
+                split.push(WatchEvent {
+                    kind: WatchKind::Create,
+                    path: event.path.clone(),
+                    root: event.root,
+                });
+            }
+            _ => split.push(event.clone()),
+        }
+    }
+    split
+}
+
+/// `Send` so it can be moved to the thread of a [`WatcherHandle`]
+pub trait Watcher: Send {
+    /// Get the changes since the last poll, it must not block.
+    fn poll(&mut self) -> Vec<WatchEvent>;
+
+    /// Whether moved files are reported as [`WatchKind::Rename`] from what
+    /// the OS knows. Otherwise the [`Debouncer`] guesses them from the paths.
+    fn exact_renames(&self) -> bool {
+        false
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatcherKind {
+    Poll,
+    /// Use the events of the OS, fallback to [`WatcherKind::Poll`] if they
+    /// aren't available.
+    Native,
+}
+
+impl WatcherKind {
+    pub fn from_name(name: &str) -> Option<WatcherKind> {
+        match name {
+            "poll" => Some(WatcherKind::Poll),
+            "native" => Some(WatcherKind::Native),
+            _ => None,
+        }
+    }
+}
+
+/// Watcher of a folder, its events are tagged with `root`
+pub fn create_watcher(
+    kind: WatcherKind,
+    root: WatchRoot,
+    folder: PathBuf,
+    filter: PathFilter,
+) -> io::Result<Box<dyn Watcher>> {
+    if kind == WatcherKind::Native {
+        #[cfg(target_os = "linux")]
+        match NativeWatcher::new(root, folder.clone(), filter.clone()) {
+            Ok(watcher) => return Ok(Box::new(watcher)),
+            Err(err) => eprintln!("[Watcher] Native watcher unavailable ({err}), using polling"),
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        eprintln!("[Watcher] Native watcher isn't supported on this platform, using polling");
+    }
+
+    Ok(Box::new(PollWatcher::new(root, folder, filter)?))
+}
+
+/// Watcher of a source folder that may not exist yet. The inner watcher is
+/// created when the folder appears, and its files are reported as created.
+/// It's dropped when the folder is removed, so the folder can come back.
+pub struct FolderWatcher {
+    kind: WatcherKind,
+    root: WatchRoot,
+    folder: PathBuf,
+    filter: PathFilter,
+    watcher: Option<Box<dyn Watcher>>,
+    exists: bool,
+}
+
+impl FolderWatcher {
+    pub fn new(
+        kind: WatcherKind,
+        root: WatchRoot,
+        folder: PathBuf,
+        filter: PathFilter,
+    ) -> FolderWatcher {
+        let mut watcher = FolderWatcher {
+            kind,
+            root,
+            folder,
+            filter,
+            watcher: None,
+            exists: false,
+        };
+        watcher.check_folder();
+        watcher
+    }
+
+    /// Create the inner watcher if the folder appeared since the last
+    /// check. Returns the files that are already inside it.
+    fn check_folder(&mut self) -> Vec<PathBuf> {
+        let exists = self.folder.is_dir();
+        let appeared = exists && !self.exists;
+        self.exists = exists;
+        if !appeared {
+            return Vec::new();
+        }
+
+        match create_watcher(
+            self.kind,
+            self.root,
+            self.folder.clone(),
+            self.filter.clone(),
+        ) {
+            Ok(watcher) => self.watcher = Some(watcher),
+            Err(err) => {
+                eprintln!("[Watcher] Can't watch {}: {err}", self.folder.display());
+                return Vec::new();
+            }
+        }
+
+        // Err(): Removed right after
+        walk_dir(&self.folder, &self.filter)
+            .map(|files| files.into_keys().collect())
+            .unwrap_or_default()
+    }
+}
+
+impl Watcher for FolderWatcher {
+    fn poll(&mut self) -> Vec<WatchEvent> {
+        let mut events = match &mut self.watcher {
+            Some(watcher) => watcher.poll(),
+            None => Vec::new(),
+        };
+
+        let created = self.check_folder();
+        if !self.exists {
+            self.watcher = None;
+        }
+
+        events.extend(created.into_iter().map(|path| WatchEvent {
+            kind: WatchKind::Create,
+            path,
+            root: self.root,
+        }));
+        events
+    }
+
+    fn exact_renames(&self) -> bool {
+        match &self.watcher {
+            Some(watcher) => watcher.exact_renames(),
+            // The inner watcher only has exact renames through inodes
+            None => cfg!(unix),
+        }
+    }
+}
+
+/// Several watchers polled as one, e.g. one per [`WatchRoot`]
+#[derive(Default)]
+pub struct MultiWatcher {
+    watchers: Vec<Box<dyn Watcher>>,
+}
+
+impl MultiWatcher {
+    pub fn push(&mut self, watcher: Box<dyn Watcher>) {
+        self.watchers.push(watcher);
+    }
+}
+
+impl Watcher for MultiWatcher {
+    fn poll(&mut self) -> Vec<WatchEvent> {
+        self.watchers
+            .iter_mut()
+            .flat_map(|watcher| watcher.poll())
+            .collect()
+    }
+
+    fn exact_renames(&self) -> bool {
+        self.watchers.iter().all(|watcher| watcher.exact_renames())
+    }
+}
@@ -0,0 +1,335 @@
+use std::ffi::OsStr;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use ahash::{HashMap, HashSet};
+use inotify::{EventMask, Inotify, WatchDescriptor, WatchMask};
+
+use super::{EventBatch, PathFilter, WatchEvent, WatchKind, WatchRoot, Watcher};
+
+/// Watcher backed by inotify, it only does work when the kernel reports a
+/// change instead of walking the whole project on every poll.
+#[derive(Debug)]
+pub struct NativeWatcher {
+    root: WatchRoot,
+    folder: PathBuf,
+    inotify: Inotify,
+    watches: HashMap<WatchDescriptor, PathBuf>,
+    filter: PathFilter,
+    /// Known files, needed to report the files of a removed folder.
+    files: HashSet<PathBuf>,
+    buffer: Vec<u8>,
+}
+
+impl NativeWatcher {
+    pub fn new(root: WatchRoot, folder: PathBuf, filter: PathFilter) -> io::Result<NativeWatcher> {
+        let mut watcher = NativeWatcher {
+            root,
+            folder: folder.clone(),
+            inotify: Inotify::init()?,
+            watches: HashMap::default(),
+            filter,
+            files: HashSet::default(),
+            buffer: vec![0; 4096],
+        };
+
+        watcher.add_dir(&folder)?;
+
+        Ok(watcher)
+    }
+
+    /// Watch the folder and all its subfolders, except the ignored ones.
+    /// Returns the files inside it.
+    fn add_dir(&mut self, dir: &Path) -> io::Result<Vec<PathBuf>> {
+        let mask = WatchMask::CREATE
+            | WatchMask::DELETE
+            | WatchMask::CLOSE_WRITE
+            | WatchMask::MOVED_FROM
+            | WatchMask::MOVED_TO;
+
+        let mut dirs = vec![dir.to_path_buf()];
+        let mut files = Vec::new();
+
+        while let Some(dir) = dirs.pop() {
+            if self.filter.is_ignored(&dir, true) {
+                continue;
+            }
+
+            let wd = self.inotify.watches().add(&dir, mask)?;
+            self.watches.insert(wd, dir.clone());
+
+            for entry in fs::read_dir(&dir)?.flatten() {
+                let path = entry.path();
+                match entry.file_type() {
+                    Ok(t) if t.is_dir() => dirs.push(path),
+                    Ok(_) if !self.filter.is_ignored(&path, false) => files.push(path),
+                    _ => (),
+                }
+            }
+        }
+
+        self.files.extend(files.iter().cloned());
+
+        Ok(files)
+    }
+
+    fn remove_dir(&mut self, dir: &Path) -> Vec<PathBuf> {
+        let removed: Vec<PathBuf> = self
+            .files
+            .iter()
+            .filter(|file| file.starts_with(dir))
+            .cloned()
+            .collect();
+
+        for file in &removed {
+            self.files.remove(file);
+        }
+
+        let watches: Vec<WatchDescriptor> = self
+            .watches
+            .iter()
+            .filter(|(_, path)| path.starts_with(dir))
+            .map(|(wd, _)| wd.clone())
+            .collect();
+        for wd in watches {
+            self.watches.remove(&wd);
+            // Err(): The kernel already dropped it because the folder was deleted
+            let _ = self.inotify.watches().remove(wd);
+        }
+
+        removed
+    }
+
+    /// The kernel dropped events because its queue was full (e.g. a `git
+    /// checkout` of a big project), so the changes are unknown. The folder
+    /// is watched again and compared with the known files; the files that
+    /// are still there are reported as modified.
+    fn rescan(&mut self) -> Vec<WatchEvent> {
+        eprintln!(
+            "[NativeWatcher] Too many changes in {}, scanning it again",
+            self.folder.display()
+        );
+
+        for (wd, _) in self.watches.drain() {
+            // Err(): The kernel already dropped it because the folder was deleted
+            let _ = self.inotify.watches().remove(wd);
+        }
+        let old_files = std::mem::take(&mut self.files);
+        let folder = self.folder.clone();
+        let files = match self.add_dir(&folder) {
+            Ok(files) => files,
+            Err(err) => {
+                eprintln!("[NativeWatcher] {}: {err}", folder.display());
+                Vec::new()
+            }
+        };
+
+        let mut events = EventBatch::default();
+        for file in &old_files {
+            if !self.files.contains(file) {
+                self.push_event(&mut events, WatchKind::Remove, file.clone());
+            }
+        }
+        for file in files {
+            let kind = if old_files.contains(&file) {
+                WatchKind::Modify
+            } else {
+                WatchKind::Create
+            };
+            self.push_event(&mut events, kind, file);
+        }
+
+        events.into_events()
+    }
+
+    /// Report the files of `to` as renamed from `from`, the known files
+    /// that are found are taken from `moved`.
+    fn push_move(
+        &mut self,
+        events: &mut EventBatch,
+        from: PathBuf,
+        to: PathBuf,
+        is_dir: bool,
+        moved: &mut HashSet<PathBuf>,
+    ) {
+        if !is_dir {
+            // The old file is unknown or ignored on an atomic save (a
+            // temporary file moved over the saved one)
+            let existed = !self.files.insert(to.clone());
+            let kind = if moved.remove(&from) && !self.filter.is_ignored(&from, false) {
+                WatchKind::Rename { from }
+            } else if existed {
+                WatchKind::Modify
+            } else {
+                WatchKind::Create
+            };
+            self.push_event(events, kind, to);
+            return;
+        }
+
+        let files = match self.add_dir(&to) {
+            Ok(files) => files,
+            Err(err) => {
+                eprintln!("[NativeWatcher] {}: {err}", to.display());
+                return;
+            }
+        };
+        for file in files {
+            let old_file = from.join(file.strip_prefix(&to).unwrap_or(&file));
+            let kind = if moved.remove(&old_file) {
+                WatchKind::Rename { from: old_file }
+            } else {
+                WatchKind::Create
+            };
+            self.push_event(events, kind, file);
+        }
+    }
+
+    fn push_event(&self, events: &mut EventBatch, kind: WatchKind, path: PathBuf) {
+        if let WatchKind::Rename { from } = &kind {
+            // Only one side of the move is watched
+            if self.filter.is_ignored(from, false) {
+                return self.push_event(events, WatchKind::Create, path);
+            }
+            if self.filter.is_ignored(&path, false) {
+                return self.push_event(events, WatchKind::Remove, from.clone());
+            }
+        }
+
+        if self.filter.is_ignored(&path, false) {
+            return;
+        }
+
+        // Only one event per file on each poll
+        events.push(WatchEvent {
+            kind,
+            path,
+            root: self.root,
+        });
+    }
+}
+
+impl Watcher for NativeWatcher {
+    fn poll(&mut self) -> Vec<WatchEvent> {
+        let mut changes: Vec<(EventMask, u32, PathBuf)> = Vec::new();
+        let mut overflow = false;
+
+        loop {
+            let inotify_events = match self.inotify.read_events(&mut self.buffer) {
+                Ok(events) => events,
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+                Err(err) => {
+                    eprintln!("[NativeWatcher] {err}");
+                    break;
+                }
+            };
+
+            let mut empty = true;
+            for event in inotify_events {
+                empty = false;
+                if event.mask.contains(EventMask::Q_OVERFLOW) {
+                    overflow = true;
+                    continue;
+                }
+                let Some(dir) = self.watches.get(&event.wd) else {
+                    continue;
+                };
+                let name: &OsStr = match event.name {
+                    Some(name) => name,
+                    None => continue,
+                };
+                changes.push((event.mask, event.cookie, dir.join(name)));
+            }
+
+            if empty {
+                break;
+            }
+        }
+
+        if overflow {
+            return self.rescan();
+        }
+
+        // A move inside the folder is a MOVED_FROM and a MOVED_TO with the
+        // same cookie, the other ones come from or go outside of it
+        let moved_to: HashSet<u32> = changes
+            .iter()
+            .filter(|(mask, _, _)| mask.contains(EventMask::MOVED_TO))
+            .map(|(_, cookie, _)| *cookie)
+            .collect();
+        // cookie -> (old path, known files moved)
+        let mut moved: HashMap<u32, (PathBuf, HashSet<PathBuf>)> = HashMap::default();
+
+        let mut events = EventBatch::default();
+        for (mask, cookie, path) in changes {
+            let is_dir = mask.contains(EventMask::ISDIR);
+
+            if mask.contains(EventMask::MOVED_FROM) && moved_to.contains(&cookie) {
+                let files = if is_dir {
+                    self.remove_dir(&path)
+                } else if self.files.remove(&path) {
+                    vec![path.clone()]
+                } else {
+                    Vec::new()
+                };
+                moved.insert(cookie, (path, HashSet::from_iter(files)));
+                continue;
+            }
+            let from = if mask.contains(EventMask::MOVED_TO) {
+                moved.remove(&cookie)
+            } else {
+                None
+            };
+            if let Some((from, mut files)) = from {
+                self.push_move(&mut events, from, path, is_dir, &mut files);
+                // Moved to an ignored folder
+                for file in files {
+                    self.push_event(&mut events, WatchKind::Remove, file);
+                }
+                continue;
+            }
+
+            let is_create = mask.intersects(EventMask::CREATE | EventMask::MOVED_TO);
+            let is_remove = mask.intersects(EventMask::DELETE | EventMask::MOVED_FROM);
+
+            if is_dir && is_create {
+                match self.add_dir(&path) {
+                    Ok(files) => {
+                        for file in files {
+                            self.push_event(&mut events, WatchKind::Create, file);
+                        }
+                    }
+                    Err(err) => eprintln!("[NativeWatcher] {}: {err}", path.display()),
+                }
+            } else if is_dir && is_remove {
+                for file in self.remove_dir(&path) {
+                    self.push_event(&mut events, WatchKind::Remove, file);
+                }
+            } else if is_create {
+                let kind = if self.files.insert(path.clone()) {
+                    WatchKind::Create
+                } else {
+                    WatchKind::Modify
+                };
+                self.push_event(&mut events, kind, path);
+            } else if is_remove {
+                self.files.remove(&path);
+                self.push_event(&mut events, WatchKind::Remove, path);
+            } else if mask.contains(EventMask::CLOSE_WRITE) {
+                let kind = if self.files.insert(path.clone()) {
+                    WatchKind::Create
+                } else {
+                    WatchKind::Modify
+                };
+                self.push_event(&mut events, kind, path);
+            }
+        }
+
+        events.into_events()
+    }
+
+    fn exact_renames(&self) -> bool {
+        true
+    }
+}
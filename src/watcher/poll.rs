@@ -1,179 +1,181 @@
 use std::cell::RefCell;
 use std::fs;
-use std::io;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{self, Read};
 #[cfg(unix)]
 use std::os::unix::fs::MetadataExt;
-#[cfg(windows)]
-use std::os::windows::fs::MetadataExt;
-use std::path::PathBuf;
-use std::sync::mpsc::Receiver;
-use std::thread;
-use std::time::{Duration, Instant};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 
 use ahash::{HashMap, HashSet};
 
 use super::utils::{walk_dir, DirIterator};
-use super::MAIN_HASHER;
+use super::{PathFilter, WatchEvent, WatchKind, WatchRoot, Watcher, MAIN_HASHER};
+
+/// What the poll compares to detect a change. The metadata is checked first,
+/// the content is only read and hashed when the metadata changed, so a `touch`
+/// or a save without changes isn't reported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileState {
+    modified: Option<SystemTime>,
+    size: u64,
+    /// 0 on platforms without inodes
+    inode: u64,
+    hash: u64,
+}
+
+impl FileState {
+    pub fn read(path: &Path) -> io::Result<FileState> {
+        let metadata = fs::metadata(path)?;
+        Ok(FileState::from_metadata(&metadata, hash_file(path)?))
+    }
 
-#[derive(Debug, Clone)]
-pub enum WatchKind {
-    Create,
-    Remove,
-    Modify,
+    fn from_metadata(metadata: &fs::Metadata, hash: u64) -> FileState {
+        #[cfg(unix)]
+        let inode = metadata.ino();
+        #[cfg(not(unix))]
+        let inode = 0;
+
+        FileState {
+            modified: metadata.modified().ok(),
+            size: metadata.len(),
+            inode,
+            hash,
+        }
+    }
+
+    fn same_metadata(&self, other: &FileState) -> bool {
+        self.modified == other.modified && self.size == other.size && self.inode == other.inode
+    }
 }
 
-static MEGABYTE: u64 = 1000000;
-static FILE_SIZE_THRESHOLD: u64 = MEGABYTE * 10;
+/// Hash of the raw bytes of the file, read in chunks so big files aren't
+/// loaded in memory.
+pub fn hash_file(path: &Path) -> io::Result<u64> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = MAIN_HASHER.with(|hasher| hasher.build_hasher());
+    let mut buffer = [0; 64 * 1024];
+
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.write(&buffer[..read]);
+    }
 
-#[derive(Debug)]
-pub struct WatchEvent {
-    pub kind: WatchKind,
-    pub path: PathBuf,
+    Ok(hasher.finish())
 }
 
 #[derive(Debug)]
 pub struct PollWatcher {
-    will_drop: bool,
+    root: WatchRoot,
     folder: PathBuf,
-    files: RefCell<HashMap<PathBuf, u64>>,
+    filter: PathFilter,
+    files: RefCell<HashMap<PathBuf, FileState>>,
 }
 
 impl PollWatcher {
-    pub fn new(folder: PathBuf) -> io::Result<PollWatcher> {
-        let files = walk_dir(&folder)?;
+    pub fn new(root: WatchRoot, folder: PathBuf, filter: PathFilter) -> io::Result<PollWatcher> {
+        let files = walk_dir(&folder, &filter)?;
 
         Ok(PollWatcher {
-            will_drop: false,
+            root,
             folder,
+            filter,
             files: RefCell::new(files),
         })
     }
+}
 
-    pub fn get_hash_with_size(path: &PathBuf, size: u64) -> u64 {
-        if size >= FILE_SIZE_THRESHOLD {
-            size
-        } else {
-            let c = match fs::read_to_string(&path) {
-                Ok(c) => c,
-                Err(_) => return 0,
-            };
-            MAIN_HASHER.with(|m| m.hash_one(c))
-        }
-    }
-
-    pub fn get_hash(path: &PathBuf) -> u64 {
-        let size = match fs::metadata(&path) {
-            #[cfg(unix)]
-            Ok(m) => m.size(),
-
-            #[cfg(windows)]
-            Ok(m) => m.file_size(),
-
-            #[cfg(not(any(unix, windows)))]
-            Ok(_) => 0,
-
-            Err(_) => 0,
-        };
-
-        Self::get_hash_with_size(path, size)
-    }
-
-    pub fn poll(&mut self) -> Vec<WatchEvent> {
-        let a = fs::read_dir(&self.folder).unwrap();
-        let a = DirIterator::new(a);
+impl Watcher for PollWatcher {
+    fn poll(&mut self) -> Vec<WatchEvent> {
+        // A removed folder has no files, they are reported as removed
+        let a = fs::read_dir(&self.folder)
+            .into_iter()
+            .flat_map(|a| DirIterator::with_filter(a, &self.filter));
 
-        let removed_files = self
-            .files
-            .borrow()
-            .iter()
-            .map(|(path, _)| path.clone())
-            .collect();
+        let removed_files = self.files.borrow().keys().cloned().collect();
         let mut removed_files: HashSet<PathBuf> = HashSet::from(removed_files);
 
         let mut events = Vec::new();
         for entry in a {
             let path = entry.path();
-            if !Self::is_valid_filename(&path) {
+            let Ok(metadata) = fs::metadata(&path) else {
+                // Removed while walking, it stays in `removed_files` and is
+                // reported as removed by this poll
                 continue;
-            }
-
-            let file_hash = Self::get_hash(&path);
+            };
 
             removed_files.remove(&path);
 
             let mut files = self.files.borrow_mut();
-            if let Some(old_hash) = files.get(&path) {
-                if *old_hash != file_hash {
-                    files.insert(path.clone(), file_hash);
+            if let Some(old_state) = files.get_mut(&path) {
+                let state = FileState::from_metadata(&metadata, old_state.hash);
+                if old_state.same_metadata(&state) {
+                    continue;
+                }
+
+                let hash = hash_file(&path).unwrap_or(0);
+                let changed = hash != old_state.hash;
+                *old_state = FileState { hash, ..state };
+                if changed {
                     events.push(WatchEvent {
                         kind: WatchKind::Modify,
                         path,
+                        root: self.root,
                     });
                 }
             } else {
-                files.insert(path.clone(), file_hash);
+                let hash = hash_file(&path).unwrap_or(0);
+                files.insert(path.clone(), FileState::from_metadata(&metadata, hash));
                 events.push(WatchEvent {
                     kind: WatchKind::Create,
                     path,
+                    root: self.root,
                 });
             }
         }
 
+        // A file moved inside the folder keeps its inode, so a create with the
+        // inode of a removed file is that file renamed
+        let mut files = self.files.borrow_mut();
+        let mut removed_inodes: HashMap<u64, PathBuf> = HashMap::default();
+        let mut removed = Vec::new();
         for entry in removed_files {
-            self.files.borrow_mut().remove(&entry);
-            events.push(WatchEvent {
-                kind: WatchKind::Remove,
-                path: entry.into(),
-            });
+            if let Some(state) = files.remove(&entry) {
+                if state.inode != 0 {
+                    removed_inodes.insert(state.inode, entry.clone());
+                }
+            }
+            removed.push(entry);
         }
 
-        events
-    }
-
-    pub fn is_valid_filename(path: &PathBuf) -> bool {
-        let is_nvim_cache = path.display().to_string().ends_with('~');
-        let is_nvim_file = path.ends_with("4913");
-        let is_output = path
-            .components()
-            .find(|c| c.as_os_str() == ".densky")
-            .is_some();
-
-        !is_nvim_cache && !is_nvim_file && !is_output
-    }
-
-    pub fn scheduling_poll<F>(&mut self, interval: Duration, rx: Receiver<()>, mut f: F)
-    where
-        F: FnMut(Vec<WatchEvent>),
-    {
-        let mut time_elapsed = Duration::ZERO;
-
-        loop {
-            let time = Instant::now();
-
-            if let Ok(_) = rx.recv_timeout(Duration::from_millis(1)) {
-                println!("[PollWatcher] Shutdown");
-                break;
+        for event in &mut events {
+            if !matches!(event.kind, WatchKind::Create) {
+                continue;
             }
 
-            if time_elapsed >= interval {
-                time_elapsed = Duration::ZERO;
-
-                let r = self.poll();
-                if r.len() != 0 {
-                    f(r);
-                }
+            let inode = files.get(&event.path).map_or(0, |state| state.inode);
+            if let Some(from) = removed_inodes.remove(&inode) {
+                removed.retain(|path| *path != from);
+                event.kind = WatchKind::Rename { from };
             }
+        }
 
-            let elapsed = time.elapsed();
-            let sleep_time = 50u64.wrapping_sub(elapsed.as_millis() as u64);
-            thread::sleep(Duration::from_millis(sleep_time as u64));
-            time_elapsed += time.elapsed();
+        for entry in removed {
+            events.push(WatchEvent {
+                kind: WatchKind::Remove,
+                path: entry,
+                root: self.root,
+            });
         }
+
+        events
     }
-}
 
-impl Drop for PollWatcher {
-    fn drop(&mut self) {
-        self.will_drop = true;
+    fn exact_renames(&self) -> bool {
+        // Without inodes a moved file can't be told apart from a new one
+        cfg!(unix)
     }
 }
@@ -2,45 +2,60 @@ use std::{fs, io, path::PathBuf};
 
 use ahash::HashMap;
 
-use super::PollWatcher;
+use super::{FileState, PathFilter};
 
 #[inline(always)]
-pub fn walk_dir(cwd: &PathBuf) -> io::Result<HashMap<PathBuf, u64>> {
+pub fn walk_dir(cwd: &PathBuf, filter: &PathFilter) -> io::Result<HashMap<PathBuf, FileState>> {
     let mut files = HashMap::default();
-    let dir = DirIterator::new(fs::read_dir(cwd)?);
+    let dir = DirIterator::with_filter(fs::read_dir(cwd)?, filter);
 
     for entry in dir.into_iter() {
         let entry = entry.path();
-
-        if PollWatcher::is_valid_filename(&entry) {
-            let hash = PollWatcher::get_hash(&entry.clone());
-            files.insert(entry, hash);
+        // Err(): Removed while walking
+        if let Ok(state) = FileState::read(&entry) {
+            files.insert(entry, state);
         }
     }
 
     Ok(files)
 }
 
-pub struct DirIterator {
+pub struct DirIterator<'a> {
     stack: Vec<fs::DirEntry>,
     current: fs::ReadDir,
+    filter: Option<&'a PathFilter>,
 }
 
-impl DirIterator {
-    pub fn new(dir: fs::ReadDir) -> DirIterator {
+impl<'a> DirIterator<'a> {
+    pub fn new(dir: fs::ReadDir) -> DirIterator<'a> {
         DirIterator {
             stack: Vec::new(),
             current: dir,
+            filter: None,
+        }
+    }
+
+    /// Skip the ignored files, the ignored folders aren't read.
+    pub fn with_filter(dir: fs::ReadDir, filter: &'a PathFilter) -> DirIterator<'a> {
+        DirIterator {
+            filter: Some(filter),
+            ..DirIterator::new(dir)
         }
     }
 }
 
-impl Iterator for DirIterator {
+impl Iterator for DirIterator<'_> {
     type Item = fs::DirEntry;
     fn next(&mut self) -> Option<Self::Item> {
         loop {
             if let Some(entry) = self.current.next().and_then(|f| f.ok()) {
-                if entry.file_type().ok()?.is_dir() {
+                let is_dir = entry.file_type().ok()?.is_dir();
+                if let Some(filter) = &self.filter {
+                    if filter.is_ignored(&entry.path(), is_dir) {
+                        continue;
+                    }
+                }
+                if is_dir {
                     self.stack.push(entry);
                 } else {
                     return Some(entry);
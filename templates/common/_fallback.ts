@@ -0,0 +1,5 @@
+import { HTTPError, HTTPRequest, StatusCode } from "densky/runtime.ts";
+
+export function ANY(_req: HTTPRequest) {
+  return new HTTPError(StatusCode.NOT_FOUND);
+}
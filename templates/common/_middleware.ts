@@ -0,0 +1,5 @@
+import { HTTPRequest } from "densky/runtime.ts";
+
+export function ANY(req: HTTPRequest) {
+  console.log(`${req.method} ${req.url}`);
+}
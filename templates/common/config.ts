@@ -0,0 +1,4 @@
+// Options of the dev server for {{name}}, loaded by the generated `dev.ts`.
+// The folders and the static prefix are set in densky.toml, the values of
+// densky.toml (or the CLI flags) replace the ones given here.
+export default {};
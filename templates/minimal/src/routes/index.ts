@@ -0,0 +1,5 @@
+import { HTTPRequest } from "densky/runtime.ts";
+
+export function GET(_req: HTTPRequest) {
+  return Response.json({ name: "{{name}}", message: "Hello from Densky!" });
+}
@@ -0,0 +1,5 @@
+import { HTTPRequest } from "densky/runtime.ts";
+
+export function GET(req: HTTPRequest) {
+  return Response.redirect(new URL("/static/index.html", req.url));
+}
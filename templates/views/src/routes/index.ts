@@ -0,0 +1,5 @@
+import { HTTPRequest, HTTPResponse } from "densky/runtime.ts";
+
+export function GET(_req: HTTPRequest) {
+  return HTTPResponse.view("index.html", { name: "{{name}}" });
+}